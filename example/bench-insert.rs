@@ -0,0 +1,65 @@
+use std::fs;
+use std::time::Instant;
+use jrdb::jrdb_type::JrDocument;
+use jrdb::jrdb_error::JrError;
+use jrdb::Database;
+
+use jrdb::jr_doc;
+
+const DOCS: i64 = 20000;
+
+fn docs() -> Vec<JrDocument> {
+  (0..DOCS)
+    .map(|i| jr_doc! {
+      "name"; String => format!("user{}", i),
+      "age"; i64 => i % 100,
+    })
+    .collect()
+}
+
+fn fresh(name: &str) -> Result<Database, JrError> {
+  let _ = fs::remove_file(format!("{}.db", name));
+  let _ = fs::remove_file(format!("{}.wal", name));
+  Database::from(name)
+}
+
+fn main() -> Result<(), JrError> {
+  //one insert and one execute per document
+  let mut db = fresh("bench_execute")?;
+  let start = Instant::now();
+  for doc in docs() {
+    db.insert("users", doc).execute()?;
+  }
+  let each = start.elapsed();
+
+  //one insert action per document
+  let mut db = fresh("bench_insert")?;
+  let start = Instant::now();
+  for doc in docs() {
+    db.insert("users", doc);
+  }
+  db.execute()?;
+  let single = start.elapsed();
+
+  //every document in one insert_many action
+  let mut db = fresh("bench_insert_many")?;
+  let start = Instant::now();
+  db.insert_many("users", docs()).execute()?;
+  let many = start.elapsed();
+
+  println!("{} documents", DOCS);
+  println!("insert, execute each: {:?}", each);
+  println!("insert, execute once: {:?}", single);
+  println!("insert_many:          {:?}", many);
+  println!(
+    "insert_many speedup: {:.1}x / {:.1}x",
+    each.as_secs_f64() / many.as_secs_f64(),
+    single.as_secs_f64() / many.as_secs_f64()
+  );
+
+  for name in ["bench_execute", "bench_insert", "bench_insert_many"].iter() {
+    let _ = fs::remove_file(format!("{}.db", name));
+    let _ = fs::remove_file(format!("{}.wal", name));
+  }
+  Ok(())
+}
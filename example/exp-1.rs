@@ -1,11 +1,12 @@
 use jrdb::jrdb_type::{AddGetValue, JrCollection, JrDocument};
+use jrdb::jrdb_error::JrError;
 use jrdb::Database;
 
 use jrdb::{exp, jr_doc};
 
-fn main() {
+fn main() -> Result<(), JrError> {
   //create file "main.db" if not exist
-  let mut db: Database = Database::from("main");
+  let mut db: Database = Database::from("main")?;
 
   //use macro to create JrDocument
   let doc = jr_doc! {
@@ -23,7 +24,7 @@ fn main() {
   //create collection "users" and admin if not exist
   db.insert("users", doc)
     .insert("admins", doc2)
-    .execute();
+    .execute()?;
 
   //update "users" with condition
   db.update(
@@ -32,22 +33,24 @@ fn main() {
       "name"; String => "Jason".into()
     },
   )
-  .condition(exp! {"name" ;== "'Joel'"})
-  .execute();
+  .condition(exp! {"name" ;== "Joel"})
+  .execute()?;
 
   //select "users"
-  let collection: JrCollection = db.select("users").execute();
+  let collection: JrCollection = db.select("users").execute()?;
   collection.print(0);
 
   //select "admins"
-  let collection: JrCollection = db.select("admins").execute();
+  let collection: JrCollection = db.select("admins").execute()?;
   collection.print(0);
 
   //delete with condition
   db.delete("users")
-    .condition(exp! {"name" ;== "'Jason'"})
-    .execute();
+    .condition(exp! {"name" ;== "Jason"})
+    .execute()?;
 
-  let collection: JrCollection = db.select("users").execute();
+  let collection: JrCollection = db.select("users").execute()?;
   collection.print(0);
+
+  Ok(())
 }
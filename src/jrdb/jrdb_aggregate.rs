@@ -0,0 +1,129 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use super::Database;
+use super::jrdb_error::JrError;
+use super::jrdb_type::{ Accumulator, JrAny, JrCollection, JrDocument, JrI64 };
+
+/// Running value of one accumulator in a group.
+enum State{
+  Count(i64),
+  Sum(i64),
+  Avg{ sum:i64, count:i64 },
+  Min(Option<JrAny>),
+  Max(Option<JrAny>),
+  Push(JrCollection),
+}
+
+impl State{
+  fn new(accumulator:&Accumulator)->Self{
+    match accumulator {
+      Accumulator::Count => State::Count(0),
+      Accumulator::Sum(_) => State::Sum(0),
+      Accumulator::Avg(_) => State::Avg{ sum:0, count:0 },
+      Accumulator::Min(_) => State::Min(None),
+      Accumulator::Max(_) => State::Max(None),
+      Accumulator::Push(_) => State::Push(JrCollection::new()),
+    }
+  }
+
+  fn add(&mut self, accumulator:&Accumulator, jr_doc:&JrDocument){
+    let value = match accumulator {
+      Accumulator::Count => None,
+      Accumulator::Sum(path)
+      | Accumulator::Avg(path)
+      | Accumulator::Min(path)
+      | Accumulator::Max(path)
+      | Accumulator::Push(path) => jr_doc.get_path(path),
+    };
+
+    match (self, value) {
+      (State::Count(count), _) => *count += 1,
+      (State::Sum(sum), Some(JrAny::JrI64(value))) => *sum = sum.saturating_add(*value.get()),
+      (State::Avg{ sum, count }, Some(JrAny::JrI64(value))) => {
+        *sum = sum.saturating_add(*value.get());
+        *count += 1;
+      },
+      (State::Min(min), Some(value)) if State::replaces(min, value, Ordering::Less) => {
+        *min = Some(value.clone());
+      },
+      (State::Max(max), Some(value)) if State::replaces(max, value, Ordering::Greater) => {
+        *max = Some(value.clone());
+      },
+      (State::Push(collec), Some(value)) => {
+        if let Accumulator::Push(path) = accumulator {
+          let mut item = JrDocument::new();
+          item.set_path(path, value.clone());
+          collec.add(item);
+        }
+      },
+      _ => {},
+    }
+  }
+
+  /// Whether `value` orders `ordering` compared to `current`, or there is
+  /// no current value yet.
+  fn replaces(current:&Option<JrAny>, value:&JrAny, ordering:Ordering)->bool{
+    current.as_ref().is_none_or(|current| Database::compare_values(Some(value), Some(current)) == ordering)
+  }
+
+  fn result(self)->Option<JrAny>{
+    match self {
+      State::Count(count) => Some(JrAny::JrI64(JrI64::new(count))),
+      State::Sum(sum) => Some(JrAny::JrI64(JrI64::new(sum))),
+      State::Avg{ count:0, .. } => None,
+      State::Avg{ sum, count } => Some(JrAny::JrI64(JrI64::new(sum / count))),
+      State::Min(min) => min,
+      State::Max(max) => max,
+      State::Push(collec) => Some(JrAny::JrCollection(collec)),
+    }
+  }
+}
+
+/// Group `docs` by the values at the `group` paths and run the accumulators
+/// over each group. Every result document holds the group values at their
+/// paths and each accumulator result under its name, groups keep the order
+/// they are first seen in.
+pub fn aggregate<I>(docs:I, group:&[String], accumulators:&[(String, Accumulator)])->Result<Vec<JrDocument>, JrError> where
+I:Iterator<Item=Result<JrDocument, JrError>>
+{
+  let mut groups:Vec<(JrDocument, Vec<State>)> = vec![];
+  //group values serialized to bytes, so values of any type can be compared
+  let mut index:BTreeMap<Vec<u8>, usize> = BTreeMap::new();
+
+  for jr_doc in docs {
+    let jr_doc = jr_doc?;
+
+    let mut key = vec![];
+    let mut group_doc = JrDocument::new();
+    for path in group.iter() {
+      match jr_doc.get_path(path) {
+        Some(value) => {
+          key.push(1);
+          key.append(&mut value.clone().get_attr_bytes(String::new(), 0));
+          group_doc.set_path(path, value.clone());
+        },
+        None => key.push(0),
+      }
+    }
+
+    let pos = *index.entry(key).or_insert_with(|| {
+      let states = accumulators.iter().map(|(_, accumulator)| State::new(accumulator)).collect();
+      groups.push((group_doc, states));
+      groups.len() - 1
+    });
+
+    let states = &mut groups[pos].1;
+    for (state, (_, accumulator)) in states.iter_mut().zip(accumulators.iter()) {
+      state.add(accumulator, &jr_doc);
+    }
+  }
+
+  Ok(groups.into_iter().map(|(mut group_doc, states)| {
+    for (state, (name, _)) in states.into_iter().zip(accumulators.iter()) {
+      if let Some(value) = state.result() {
+        group_doc.set_path(name, value);
+      }
+    }
+    group_doc
+  }).collect())
+}
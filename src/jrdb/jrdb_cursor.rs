@@ -0,0 +1,217 @@
+use std::cmp::Ordering;
+use std::collections::{ BTreeSet, VecDeque };
+use super::{ Action, ActionType, Database, HeaderDetail, Projection };
+use super::jrdb_error::JrError;
+use super::jrdb_file::FILE_HEADER_LEN;
+use super::jrdb_plan::{ QueryPlan, Scan };
+use super::jrdb_type::{ AddGetValue, JrDocument };
+
+/// Documents of a select read one at a time, returned by `Database::iter`.
+///
+/// The collection is walked header by header, a document is only decoded
+/// when the cursor reaches it, so dropping the cursor early leaves the rest
+/// of the collection unread. With `sort_by` every matching document has to
+/// be read before the first one is returned.
+pub struct Cursor<'a>{
+  db:&'a mut Database,
+  action:Action,
+  projection:Projection,
+  started:bool,
+  //collection being read, None when it does not exist or the cursor is done
+  collection:Option<HeaderDetail>,
+  //position of the next document header and where the documents end
+  pos:usize,
+  end:usize,
+  //only documents with these _id are read, None reads every document
+  ids:Option<BTreeSet<String>>,
+  scan:Scan,
+  //documents read and tested against the condition
+  examined:usize,
+  //every matching document in order, only used with sort_by
+  sorted:Option<VecDeque<JrDocument>>,
+  skipped:usize,
+  returned:usize,
+  last_id:Option<String>,
+}
+
+impl<'a> Cursor<'a>{
+  pub(crate) fn new(db:&'a mut Database, action:Action)->Self{
+    let projection = Projection::new(&action);
+    Cursor{
+      db,
+      action,
+      projection,
+      started:false,
+      collection:None,
+      pos:0,
+      end:0,
+      ids:None,
+      scan:Scan::Full,
+      examined:0,
+      sorted:None,
+      skipped:0,
+      returned:0,
+      last_id:None,
+    }
+  }
+
+  /// `_id` of the last document returned, give it to `Database::after` to
+  /// continue from there with another select.
+  pub fn last_id(&self)->Option<&str>{
+    self.last_id.as_deref()
+  }
+
+  fn start(&mut self)->Result<(), JrError>{
+    if !matches!(self.action.action_type, ActionType::Select) {
+      return Err(JrError::InvalidQuery("iter needs a select".into()));
+    }
+
+    let root = self.db.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let collection_name = self.action.from.split('.').next().unwrap_or_default();
+    let mut collection = self.db.get_by_key_from_doc(&root, collection_name, 1)?;
+    if !collection.found {
+      return Ok(());
+    }
+    self.pos = collection.content_start;
+    self.end = collection.content_end;
+
+    (self.ids, self.scan) = self.db.plan_scan(&self.action);
+    if let Some(ids) = self.ids.as_ref().filter(|ids| ids.len() <= 1) {
+      //a single document is found by walking the headers only
+      let header = match ids.iter().next() {
+        Some(id) => self.db.get_pos_by_key(self.pos, self.end, id, 0)?,
+        None => return Ok(()),
+      };
+      if !header.found {
+        return Ok(());
+      }
+      self.pos = header.header_start;
+      self.end = header.content_end;
+    }
+
+    let sorted = !self.action.sort.is_empty();
+    //the after document with its position, it does not have to match the
+    //condition
+    let mut after_doc = None;
+    if let Some(after) = self.action.after.as_deref() {
+      //looked up in the whole collection, the range may be narrowed to a
+      //single candidate above
+      let mut header = self.db.get_pos_by_key(collection.content_start, collection.content_end, after, 0)?;
+      if !header.found {
+        return Err(JrError::NotFound(after.into()));
+      }
+      if sorted {
+        let jr_doc = self.db.read_document(&mut collection, &mut header, Some(&self.projection))?;
+        after_doc = Some((header.header_start, jr_doc));
+      }else{
+        self.pos = self.pos.max(header.content_end);
+      }
+    }
+    self.collection = Some(collection);
+
+    if sorted {
+      let mut matched = vec![];
+      while let Some(found) = self.next_match()? {
+        matched.push(found);
+      }
+      //documents with the same sort values keep the collection order
+      let sort = &self.action.sort;
+      let order = |a:&(usize, JrDocument), b:&(usize, JrDocument)| {
+        Database::compare_docs(&a.1, &b.1, sort).then(a.0.cmp(&b.0))
+      };
+      matched.sort_by(order);
+
+      if let Some(after_doc) = after_doc.as_ref() {
+        matched.retain(|found| order(found, after_doc) == Ordering::Greater);
+      }
+      self.sorted = Some(matched.into_iter().map(|(_, jr_doc)| jr_doc).collect());
+    }
+    Ok(())
+  }
+
+  /// Read documents until one matches the condition, returns it with the
+  /// position of its header.
+  fn next_match(&mut self)->Result<Option<(usize, JrDocument)>, JrError>{
+    while let Some(collection) = self.collection.as_mut() {
+      if self.pos >= self.end {
+        self.collection = None;
+        break;
+      }
+      let mut header = self.db.get_child_header_by_pos(collection, self.pos)?;
+      self.pos = header.content_end;
+      if self.ids.as_ref().is_some_and(|ids| !ids.contains(&header.key)) {
+        continue;
+      }
+
+      self.examined += 1;
+      let jr_doc = self.db.read_document(collection, &mut header, Some(&self.projection))?;
+      if self.action.condition.result(&jr_doc) {
+        return Ok(Some((header.header_start, jr_doc)));
+      }
+    }
+    Ok(None)
+  }
+
+  /// How the documents were found and how many were read so far.
+  pub(crate) fn plan(&mut self)->Result<QueryPlan, JrError>{
+    let estimated = match self.ids.as_ref() {
+      Some(ids) => ids.len(),
+      None => {
+        let collection_name = self.action.from.split('.').next().unwrap_or_default();
+        self.db.count_documents(collection_name)?
+      },
+    };
+    Ok(QueryPlan{
+      scan:self.scan.clone(),
+      estimated,
+      examined:self.examined,
+      returned:self.returned,
+      sorted_in_memory:!self.action.sort.is_empty(),
+    })
+  }
+
+  fn id_of(jr_doc:&JrDocument)->Option<String>{
+    jr_doc.get_value("_id").ok()
+  }
+}
+
+impl Iterator for Cursor<'_>{
+  type Item = Result<JrDocument, JrError>;
+
+  fn next(&mut self)->Option<Self::Item>{
+    if self.action.limit.is_some_and(|limit| self.returned >= limit) {
+      return None;
+    }
+
+    if !self.started {
+      self.started = true;
+      if let Err(e) = self.start() {
+        self.collection = None;
+        self.sorted = None;
+        return Some(Err(e));
+      }
+    }
+
+    loop {
+      let jr_doc = match self.sorted.as_mut() {
+        Some(sorted) => sorted.pop_front()?,
+        None => match self.next_match() {
+          Ok(Some((_, jr_doc))) => jr_doc,
+          Ok(None) => return None,
+          Err(e) => {
+            self.collection = None;
+            return Some(Err(e));
+          },
+        },
+      };
+
+      if self.skipped < self.action.skip {
+        self.skipped += 1;
+        continue;
+      }
+      self.returned += 1;
+      self.last_id = Cursor::id_of(&jr_doc);
+      return Some(Ok(self.projection.apply(jr_doc)));
+    }
+  }
+}
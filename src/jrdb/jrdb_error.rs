@@ -0,0 +1,70 @@
+use std::error::Error;
+use std::fmt::{ Display, Formatter };
+use std::fmt;
+use std::io;
+
+/// Error returned by the database instead of panicking.
+#[derive(Debug)]
+pub enum JrError{
+  /// Reading or writing the database file failed.
+  Io(io::Error),
+  /// The bytes at `offset` are not a valid header or value.
+  Corrupt{ offset:usize },
+  /// A key or collection name longer than 255 bytes.
+  KeyTooLong(String),
+  /// The value at the path is not of the type needed by the action.
+  TypeMismatch(String),
+  /// The collection, document or path does not exist.
+  NotFound(String),
+  /// A document with the same `_id` already exists in the collection.
+  DuplicateId(String),
+  /// A value already used by another document of a unique index, holds the
+  /// collection and field of the index.
+  DuplicateKey(String),
+  /// The queued actions can not be run this way.
+  InvalidQuery(String),
+  /// The file does not start with the jrdb magic bytes.
+  NotDatabase,
+  /// The file was written by a newer format version.
+  UnsupportedVersion(u16),
+  /// The file uses feature flags this build does not know.
+  UnsupportedFeatures(u32),
+  /// An execute failed with `error` and undoing its changes failed with
+  /// `rollback`, the database has to be opened again before it is used.
+  RollbackFailed{ error:Box<JrError>, rollback:Box<JrError> },
+}
+
+impl Display for JrError{
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    match self {
+      JrError::Io(e) => write!(f, "io error: {}", e),
+      JrError::Corrupt{ offset } => write!(f, "database is corrupt at byte {}", offset),
+      JrError::KeyTooLong(key) => write!(f, "key is longer than 255 bytes: {}", key),
+      JrError::TypeMismatch(path) => write!(f, "value has an unexpected type: {}", path),
+      JrError::NotFound(path) => write!(f, "not found: {}", path),
+      JrError::DuplicateId(id) => write!(f, "duplicate _id: {}", id),
+      JrError::DuplicateKey(index) => write!(f, "duplicate value for unique index {}", index),
+      JrError::InvalidQuery(reason) => write!(f, "invalid query: {}", reason),
+      JrError::NotDatabase => write!(f, "file is not a jrdb database"),
+      JrError::UnsupportedVersion(version) => write!(f, "unsupported database format version {}", version),
+      JrError::UnsupportedFeatures(flags) => write!(f, "unsupported database feature flags {:#x}", flags),
+      JrError::RollbackFailed{ error, rollback } => write!(f, "{}, undoing the changes failed: {}", error, rollback),
+    }
+  }
+}
+
+impl Error for JrError{
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      JrError::Io(e) => Some(e),
+      JrError::RollbackFailed{ error, .. } => Some(error.as_ref()),
+      _ => None,
+    }
+  }
+}
+
+impl From<io::Error> for JrError{
+  fn from(e: io::Error) -> Self {
+    JrError::Io(e)
+  }
+}
@@ -0,0 +1,167 @@
+use std::fs::{ self, File, OpenOptions };
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+use super::jrdb_error::JrError;
+
+/// Every database file starts with these bytes.
+pub const MAGIC:[u8;4] = *b"JRDB";
+
+/// Version of the on-disk layout written by this build.
+///
+/// - 0: no file header, the file starts with the root document
+/// - 1: magic bytes, version and feature flags
+/// - 2: adds the checkpoint sequence matching the write-ahead log
+pub const FORMAT_VERSION:u16 = 2;
+
+/// Set once the database holds indexes, a build that does not maintain them
+/// must not write to the file.
+pub const FLAG_INDEXES:u32 = 1;
+
+/// Feature flags understood by this build, a file using any other flag is
+/// refused instead of misparsed.
+pub const KNOWN_FLAGS:u32 = FLAG_INDEXES;
+
+/// Position of the feature flags in the file header.
+pub const FLAGS_OFFSET:usize = 6;
+
+/// Magic bytes, version (u16), feature flags (u32) and checkpoint sequence
+/// (u64), the root document starts right after it.
+pub const FILE_HEADER_LEN:usize = 18;
+
+/// Length of the file header written by format version 1.
+const V1_HEADER_LEN:usize = 10;
+
+/// Root document named "root" written by databases created before the file
+/// header existed.
+const LEGACY_ROOT:[u8;11] = [0, 0, 4, 0, 0, 0, 11, 114, 111, 111, 116];
+
+pub struct FileHeader{
+  pub version:u16,
+  pub flags:u32,
+  /// Incremented by every checkpoint, a write-ahead log only applies to the
+  /// file with the same sequence.
+  pub checkpoint:u64,
+}
+
+impl FileHeader{
+  /// Header for a file written in the current format.
+  pub fn current()->Self{
+    FileHeader{
+      version:FORMAT_VERSION,
+      flags:0,
+      checkpoint:0,
+    }
+  }
+
+  pub fn get_bytes(&self)->Vec<u8>{
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&self.version.to_be_bytes());
+    data.extend_from_slice(&self.flags.to_be_bytes());
+    data.extend_from_slice(&self.checkpoint.to_be_bytes());
+    data
+  }
+
+  /// Read the file header at the start of `data`. A file written by an older
+  /// format has its header upgraded in `data`, the returned `version` is the
+  /// one found in the file so the caller knows it has to be rewritten.
+  pub fn read(data:&mut Vec<u8>)->Result<FileHeader, JrError>{
+    if !data.starts_with(&MAGIC) {
+      if data.len() >= LEGACY_ROOT.len() && data[0..3] == LEGACY_ROOT[0..3] && data[7..11] == LEGACY_ROOT[7..11] {
+        data.splice(0..0, FileHeader::current().get_bytes());
+        return Ok(FileHeader{
+          version:0,
+          flags:0,
+          checkpoint:0,
+        });
+      }
+      return Err(JrError::NotDatabase);
+    }
+
+    if data.len() < V1_HEADER_LEN {
+      return Err(JrError::Corrupt{ offset:0 });
+    }
+
+    let version = u16::from_be_bytes([data[4], data[5]]);
+    let flags = u32::from_be_bytes([data[6], data[7], data[8], data[9]]);
+    if version > FORMAT_VERSION {
+      return Err(JrError::UnsupportedVersion(version));
+    }
+    if flags & !KNOWN_FLAGS != 0 {
+      return Err(JrError::UnsupportedFeatures(flags & !KNOWN_FLAGS));
+    }
+
+    if version < 2 {
+      let header = FileHeader{
+        flags,
+        ..FileHeader::current()
+      };
+      data.splice(0..V1_HEADER_LEN, header.get_bytes());
+      return Ok(FileHeader{
+        version,
+        ..header
+      });
+    }
+
+    if data.len() < FILE_HEADER_LEN {
+      return Err(JrError::Corrupt{ offset:0 });
+    }
+    let mut checkpoint = [0;8];
+    checkpoint.copy_from_slice(&data[10..18]);
+
+    Ok(FileHeader{
+      version,
+      flags,
+      checkpoint:u64::from_be_bytes(checkpoint),
+    })
+  }
+}
+
+/// Bytes of a new database, the file header and an empty root document.
+pub fn new_database()->Vec<u8>{
+  let mut data = FileHeader::current().get_bytes();
+  data.extend_from_slice(&LEGACY_ROOT);
+  data
+}
+
+/// Path of the temporary file written before it replaces `path`.
+pub fn tmp_path(path:&Path)->PathBuf{
+  let mut tmp = path.as_os_str().to_owned();
+  tmp.push(".tmp");
+  PathBuf::from(tmp)
+}
+
+/// Replace the file at `path` with `data` so that a crash leaves either the
+/// old or the new content, never a torn file. The data goes to a temporary
+/// file which is synced and renamed over `path`, then the directory is synced
+/// so the rename itself survives a crash.
+pub fn write_atomic(path:&Path, data:&[u8])->Result<(), JrError>{
+  let tmp = tmp_path(path);
+  let mut file = OpenOptions::new()
+  .write(true)
+  .create(true)
+  .truncate(true).open(&tmp)?;
+
+  file.write_all(data)?;
+  file.sync_all()?;
+  drop(file);
+
+  fs::rename(&tmp, path)?;
+  sync_dir(path)
+}
+
+#[cfg(unix)]
+pub(crate) fn sync_dir(path:&Path)->Result<(), JrError>{
+  let dir = match path.parent() {
+    Some(dir) if !dir.as_os_str().is_empty() => dir,
+    _ => Path::new("."),
+  };
+  File::open(dir)?.sync_all()?;
+  Ok(())
+}
+
+//directories can not be opened for syncing on other platforms, the rename
+//is made durable by the file system there
+#[cfg(not(unix))]
+pub(crate) fn sync_dir(_path:&Path)->Result<(), JrError>{
+  Ok(())
+}
@@ -0,0 +1,413 @@
+use std::collections::{ BTreeMap, BTreeSet, HashMap };
+use std::ops::{ Bound, RangeBounds };
+use super::jrdb_type::{ AddGetValue, ConditionType, JrAny, JrCondition, JrDocument, JrOperand };
+
+/// Name of the root document holding every index, collection names can not
+/// start with `$` so it never clashes with one.
+pub const INDEXES_KEY:&str = "$indexes";
+
+/// Pages the stored entries of an index are spread over, an entry is found
+/// by walking the page headers and then its page only.
+const ENTRY_PAGES:u32 = 256;
+
+/// Page of the stored entry of the document `id`. FNV-1a keeps the page the
+/// same across builds, the pages are written in the database file.
+pub fn entry_page(id:&str)->String{
+  let mut hash:u32 = 0x811c9dc5;
+  for byte in id.bytes() {
+    hash = (hash ^ byte as u32).wrapping_mul(0x01000193);
+  }
+  (hash % ENTRY_PAGES).to_string()
+}
+
+/// Value of an indexed field, ordered like `Database::sort_by`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IndexKey{
+  //a field after the first one of a compound index the document does not have
+  Missing,
+  I64(i64),
+  String(String),
+  //documents and collections in their serialized form
+  Other(Vec<u8>),
+}
+
+impl IndexKey{
+  pub fn new(value:&JrAny)->Self{
+    match value {
+      JrAny::JrI64(value) => IndexKey::I64(*value.get()),
+      JrAny::JrString(value) => IndexKey::String(value.get().clone()),
+      _ => IndexKey::Other(value.clone().get_attr_bytes(String::new(), 0)),
+    }
+  }
+
+  fn all_i64()->KeyRange{
+    (Bound::Included(IndexKey::I64(i64::MIN)), Bound::Included(IndexKey::I64(i64::MAX)))
+  }
+
+  fn all_string()->KeyRange{
+    (Bound::Included(IndexKey::String(String::new())), Bound::Excluded(IndexKey::Other(vec![])))
+  }
+}
+
+type KeyRange = (Bound<IndexKey>, Bound<IndexKey>);
+
+/// Ranges of one field an expression can be true in, with whether it is an
+/// `==` expression.
+type Constraint = (bool, Vec<KeyRange>);
+
+/// Sorted index of one or more fields of a collection, kept in memory and
+/// stored in the `$indexes` document of the database as `_id` => values.
+///
+/// A document has one key holding the value of each field. A field going
+/// through a collection, like `tags.name`, is multikey: the document gets a
+/// key for every item of the collection.
+#[derive(Clone)]
+pub struct Index{
+  /// Key of the index in the `$indexes` document.
+  pub name:String,
+  pub collection:String,
+  pub fields:Vec<String>,
+  /// No two documents can have the same key, keys missing a field are not
+  /// checked.
+  pub unique:bool,
+  entries:BTreeMap<Vec<IndexKey>, BTreeSet<String>>,
+  keys:HashMap<String, BTreeSet<Vec<IndexKey>>>,
+}
+
+impl Index{
+  pub fn new(name:&str, collection:&str, fields:&[String], unique:bool)->Self{
+    Index{
+      name:name.into(),
+      collection:collection.into(),
+      fields:fields.to_vec(),
+      unique,
+      entries:BTreeMap::new(),
+      keys:HashMap::new(),
+    }
+  }
+
+  /// Collection and fields of the index, as `users.tenant_id,created_at`.
+  pub fn target(&self)->String{
+    format!("{}.{}", self.collection, self.fields.join(","))
+  }
+
+  /// Values of each field of `jr_doc`, a multikey field can have many.
+  fn values_of<'a>(&self, jr_doc:&'a JrDocument)->Vec<Vec<&'a JrAny>>{
+    self.fields.iter().map(|field| jr_doc.get_path_all(field)).collect()
+  }
+
+  /// Every combination of `values`, none when the first field is missing as
+  /// no condition using the index can match the document.
+  fn keys_from(values:&[Vec<&JrAny>])->BTreeSet<Vec<IndexKey>>{
+    let mut keys = BTreeSet::new();
+    if values.first().is_none_or(|first| first.is_empty()) {
+      return keys;
+    }
+    keys.insert(vec![]);
+    for field_values in values.iter() {
+      let mut field_keys:BTreeSet<IndexKey> = field_values.iter().map(|value| IndexKey::new(value)).collect();
+      if field_keys.is_empty() {
+        field_keys.insert(IndexKey::Missing);
+      }
+      keys = keys.into_iter().flat_map(|key| {
+        field_keys.iter().map(move |field_key| {
+          let mut key = key.clone();
+          key.push(field_key.clone());
+          key
+        })
+      }).collect();
+    }
+    keys
+  }
+
+  /// Keys of the document `jr_doc`.
+  pub fn keys_of(&self, jr_doc:&JrDocument)->BTreeSet<Vec<IndexKey>>{
+    Index::keys_from(&self.values_of(jr_doc))
+  }
+
+  /// Values of `jr_doc` as stored in the `$indexes` document, `None` when the
+  /// document is not indexed. Each field is stored under its position with
+  /// its values numbered the same way.
+  pub fn stored(&self, jr_doc:&JrDocument)->Option<JrDocument>{
+    let values = self.values_of(jr_doc);
+    if values.first().is_none_or(|first| first.is_empty()) {
+      return None;
+    }
+    let mut stored = JrDocument::new();
+    for (pos, field_values) in values.iter().enumerate() {
+      let mut field_doc = JrDocument::new();
+      for (num, value) in field_values.iter().enumerate() {
+        field_doc.add_any(&num.to_string(), (*value).clone());
+      }
+      stored.add_value(&pos.to_string(), field_doc);
+    }
+    Some(stored)
+  }
+
+  /// Index the document `id` from the values written by `stored`.
+  pub fn load(&mut self, id:&str, stored:&JrDocument){
+    let mut values:Vec<Vec<&JrAny>> = vec![vec![]; self.fields.len()];
+    for (pos, field_values) in values.iter_mut().enumerate() {
+      if let Some(JrAny::JrDocument(field_doc)) = stored.get_any(&pos.to_string()) {
+        let mut num = 0;
+        while let Some(value) = field_doc.get_any(&num.to_string()) {
+          field_values.push(value);
+          num += 1;
+        }
+      }
+    }
+    self.insert(id, Index::keys_from(&values));
+  }
+
+  /// Index the document `id` under `keys`, no keys when the document is not
+  /// indexed. Returns false when the index did not change.
+  pub fn insert(&mut self, id:&str, keys:BTreeSet<Vec<IndexKey>>)->bool{
+    let unchanged = match self.keys.get(id) {
+      Some(current) => *current == keys,
+      None => keys.is_empty(),
+    };
+    if unchanged {
+      return false;
+    }
+    self.remove(id);
+    if !keys.is_empty() {
+      for key in keys.iter() {
+        self.entries.entry(key.clone()).or_default().insert(id.into());
+      }
+      self.keys.insert(id.into(), keys);
+    }
+    true
+  }
+
+  /// Whether indexing `keys` for the document `id` breaks a unique index.
+  pub fn conflicts(&self, id:&str, keys:&BTreeSet<Vec<IndexKey>>)->bool{
+    if !self.unique {
+      return false;
+    }
+    keys.iter()
+      .filter(|key| !key.contains(&IndexKey::Missing))
+      .filter_map(|key| self.entries.get(key))
+      .any(|ids| ids.iter().any(|elem| elem != id))
+  }
+
+  /// Remove the document `id`, returns false when it was not indexed.
+  pub fn remove(&mut self, id:&str)->bool{
+    let keys = match self.keys.remove(id) {
+      Some(keys) => keys,
+      None => return false,
+    };
+    for key in keys.iter() {
+      if let Some(ids) = self.entries.get_mut(key) {
+        ids.remove(id);
+        if ids.is_empty() {
+          self.entries.remove(key);
+        }
+      }
+    }
+    true
+  }
+
+  /// Constraints of the fields the index can use: every field with an `==`
+  /// followed by at most one field with a range. The constraint driving the
+  /// scan of a field comes first.
+  fn plan<'a>(&self, constraints:&'a HashMap<String, Vec<Constraint>>)->Vec<&'a [Constraint]>{
+    let mut slots = vec![];
+    for field in self.fields.iter() {
+      match constraints.get(field) {
+        Some(field_constraints) => {
+          slots.push(field_constraints.as_slice());
+          if !field_constraints[0].0 {
+            break;
+          }
+        },
+        None => break,
+      }
+    }
+    slots
+  }
+
+  /// Add the ids of the keys matching `slots` that start with `prefix`.
+  fn scan(&self, slots:&[&[Constraint]], prefix:&mut Vec<IndexKey>, ids:&mut BTreeSet<String>){
+    let depth = prefix.len();
+    let driving = match slots.get(depth) {
+      Some(field_constraints) => &field_constraints[0].1,
+      None => {
+        self.scan_from(slots, prefix, prefix.clone(), Bound::Unbounded, ids);
+        return;
+      },
+    };
+
+    for (start, end) in driving.iter() {
+      match (start, end) {
+        (Bound::Included(start), Bound::Included(end)) if start == end => {
+          //a single value, the next field narrows the scan down further
+          prefix.push(start.clone());
+          self.scan(slots, prefix, ids);
+          prefix.pop();
+        },
+        _ => {
+          let mut from = prefix.clone();
+          if let Bound::Included(start) | Bound::Excluded(start) = start {
+            from.push(start.clone());
+          }
+          self.scan_from(slots, prefix, from, end.as_ref(), ids);
+        },
+      }
+    }
+  }
+
+  /// Walk the keys from `from` while they start with `prefix` and the next
+  /// field is before `end`.
+  fn scan_from(&self, slots:&[&[Constraint]], prefix:&[IndexKey], from:Vec<IndexKey>, end:Bound<&IndexKey>, ids:&mut BTreeSet<String>){
+    let depth = prefix.len();
+    for (key, entry) in self.entries.range(from..) {
+      if !key.starts_with(prefix) {
+        break;
+      }
+      let past_end = match (key.get(depth), end) {
+        (Some(value), Bound::Included(end)) => value > end,
+        (Some(value), Bound::Excluded(end)) => value >= end,
+        _ => false,
+      };
+      if past_end {
+        break;
+      }
+      if Index::matches(slots, key) {
+        ids.extend(entry.iter().cloned());
+      }
+    }
+  }
+
+  fn matches(slots:&[&[Constraint]], key:&[IndexKey])->bool{
+    slots.iter().enumerate().all(|(pos, field_constraints)| {
+      field_constraints.iter().all(|(_, ranges)| {
+        key.get(pos).is_some_and(|value| ranges.iter().any(|range| range.contains(value)))
+      })
+    })
+  }
+}
+
+/// Ranges of keys where `field op literal` can be true, `None` when an index
+/// can not answer the expression. Conditions compare a number with a string
+/// by value, so a literal of one type also takes every key of the other
+/// type that may match it. The negated operators also match documents
+/// without the field, which have no key, so they are never answered.
+fn ranges(cond_type:&ConditionType, literal:&JrAny)->Option<Vec<KeyRange>>{
+  let op = match cond_type {
+    ConditionType::Eq | ConditionType::Gt | ConditionType::GtE | ConditionType::St | ConditionType::StE => cond_type,
+    _ => return None,
+  };
+
+  match literal {
+    JrAny::JrI64(value) => Some(vec![
+      range(op, IndexKey::I64(*value.get()), IndexKey::all_i64()),
+      IndexKey::all_string(),
+    ]),
+    JrAny::JrString(value) => Some(vec![
+      range(op, IndexKey::String(value.get().clone()), IndexKey::all_string()),
+      match value.get().parse::<i64>() {
+        Ok(num) => range(op, IndexKey::I64(num), IndexKey::all_i64()),
+        Err(_) => IndexKey::all_i64(),
+      },
+    ]),
+    _ => None,
+  }
+}
+
+/// Keys `op key` is true for, out of the keys of the same type `(min, max)`.
+fn range(op:&ConditionType, key:IndexKey, (min, max):KeyRange)->KeyRange{
+  match op {
+    ConditionType::Eq => (Bound::Included(key.clone()), Bound::Included(key)),
+    ConditionType::Gt => (Bound::Excluded(key), max),
+    ConditionType::GtE => (Bound::Included(key), max),
+    ConditionType::St => (min, Bound::Excluded(key)),
+    _ => (min, Bound::Included(key)),
+  }
+}
+
+/// Ids of the documents that can match `condition` using `indexes` with the
+/// targets of the indexes used, `None` when the collection has to be
+/// scanned. The condition is still evaluated on every document found, the
+/// ids only narrow down which are read.
+///
+/// The expressions joined by an `and` are answered by the index using the
+/// most of their fields, a compound index is used from its first field on.
+pub fn candidates(indexes:&[&Index], condition:&JrCondition)->Option<(BTreeSet<String>, Vec<String>)>{
+  match condition.cond_type() {
+    ConditionType::And => {
+      let conditions:Vec<&JrCondition> = condition.conditions().iter().collect();
+      let mut result = best_index(indexes, &conditions)
+        .map(|(index, constraints)| (index_ids(index, &constraints), vec![index.target()]));
+      for elem in conditions.iter() {
+        if !matches!(elem.cond_type(), ConditionType::And | ConditionType::Or) {
+          continue;
+        }
+        if let Some((ids, used)) = candidates(indexes, elem) {
+          result = Some(match result {
+            Some((result, mut result_used)) => {
+              merge_used(&mut result_used, used);
+              (result.intersection(&ids).cloned().collect(), result_used)
+            },
+            None => (ids, used),
+          });
+        }
+      }
+      result
+    },
+    ConditionType::Or => {
+      if condition.conditions().is_empty() {
+        return None;
+      }
+      let mut result = BTreeSet::new();
+      let mut result_used = vec![];
+      for elem in condition.conditions().iter() {
+        let (mut ids, used) = candidates(indexes, elem)?;
+        result.append(&mut ids);
+        merge_used(&mut result_used, used);
+      }
+      Some((result, result_used))
+    },
+    _ => best_index(indexes, &[condition])
+      .map(|(index, constraints)| (index_ids(index, &constraints), vec![index.target()])),
+  }
+}
+
+fn merge_used(result_used:&mut Vec<String>, used:Vec<String>){
+  for target in used {
+    if !result_used.contains(&target) {
+      result_used.push(target);
+    }
+  }
+}
+
+/// The index using the most fields of the `field op literal` expressions in
+/// `conditions`, with the constraints of those fields.
+fn best_index<'a>(indexes:&[&'a Index], conditions:&[&JrCondition])->Option<(&'a Index, HashMap<String, Vec<Constraint>>)>{
+  let mut constraints:HashMap<String, Vec<Constraint>> = HashMap::new();
+  for elem in conditions.iter() {
+    if let (JrOperand::Field(path), JrOperand::Literal(literal)) = elem.expression() {
+      if let Some(ranges) = ranges(elem.cond_type(), literal) {
+        let eq = matches!(elem.cond_type(), ConditionType::Eq);
+        let field_constraints = constraints.entry(path.clone()).or_default();
+        field_constraints.push((eq, ranges));
+        //an == drives the scan of the field
+        field_constraints.sort_by_key(|(eq, _)| !eq);
+      }
+    }
+  }
+
+  let mut best:Option<(&Index, usize)> = None;
+  for index in indexes.iter() {
+    let used = index.plan(&constraints).len();
+    if used > 0 && best.is_none_or(|(_, best_used)| used > best_used) {
+      best = Some((index, used));
+    }
+  }
+  best.map(|(index, _)| (index, constraints))
+}
+
+fn index_ids(index:&Index, constraints:&HashMap<String, Vec<Constraint>>)->BTreeSet<String>{
+  let mut ids = BTreeSet::new();
+  index.scan(&index.plan(constraints), &mut vec![], &mut ids);
+  ids
+}
@@ -0,0 +1,29 @@
+/// How a select finds the documents it tests against its condition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scan{
+  /// Every document of the collection is read.
+  Full,
+  /// Only the document with this `_id` is read.
+  Id(String),
+  /// Only the documents found with these indexes are read, each named by its
+  /// collection and fields like `users.tenant_id,created_at`.
+  Index(Vec<String>),
+}
+
+/// Plan of a select and what running it took, returned by
+/// `Database::explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPlan{
+  pub scan:Scan,
+  /// Documents the scan is expected to read: every document of the
+  /// collection for a full scan, the documents found by the `_id` or the
+  /// indexes otherwise.
+  pub estimated:usize,
+  /// Documents read and tested against the condition.
+  pub examined:usize,
+  /// Documents returned after `skip` and `limit`.
+  pub returned:usize,
+  /// Every match was read and sorted in memory before the first one was
+  /// returned, true with `sort_by` as no index gives a sorted order.
+  pub sorted_in_memory:bool,
+}
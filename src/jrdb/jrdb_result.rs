@@ -0,0 +1,25 @@
+use super::jrdb_type::JrCollection;
+
+/// What one action did, returned for each queued action by
+/// `Database::execute_results`.
+#[derive(Clone, Default)]
+pub struct ExecuteResult{
+  /// `_id` given to the document inserted by an insert or an upsert.
+  pub inserted_ids:Vec<String>,
+  /// Documents matching the condition of an update, upsert, replace or
+  /// delete.
+  pub matched:usize,
+  /// Documents changed by an update, upsert or replace, a document already
+  /// holding the new values is matched but not modified.
+  pub modified:usize,
+  /// Documents removed by a delete.
+  pub deleted:usize,
+  /// Documents found by a select or an aggregate.
+  pub documents:JrCollection,
+  /// With `Database::returning`, the documents matched by an update,
+  /// replace or delete as they were before the action.
+  pub before:JrCollection,
+  /// With `Database::returning`, the documents written by an insert,
+  /// update, upsert or replace as they are after the action.
+  pub after:JrCollection,
+}
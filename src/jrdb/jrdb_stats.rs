@@ -0,0 +1,14 @@
+/// Size of a collection and the indexes on it, returned by
+/// `Database::collection_stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionStats{
+  /// Documents in the collection.
+  pub documents:usize,
+  /// Bytes taken by the documents, headers included.
+  pub size:usize,
+  /// `size` divided by `documents`, 0 for an empty collection.
+  pub avg_document_size:usize,
+  /// Indexes of the collection, each named by its collection and fields
+  /// like `users.tenant_id,created_at`.
+  pub indexes:Vec<String>,
+}
@@ -1,9 +1,12 @@
 use std::collections::BTreeMap;
 use byteorder::{WriteBytesExt, BigEndian};
+use std::cmp::Ordering;
 use std::fmt::{ Display, Formatter };
 use std::fmt;
 use super::HeaderDetail;
 use super::Database;
+use super::check_key;
+use super::jrdb_error::JrError;
 
 #[derive(Clone)]
 pub enum JrAny{
@@ -13,6 +16,73 @@ pub enum JrAny{
   JrI64(JrI64),
 }
 
+impl JrAny{
+  /// Serialize the value together with its attribute header, the header is
+  /// written at `depth` and nested content one level below it.
+  pub fn get_attr_bytes(&mut self, key:String, depth:u8)->Vec<u8>{
+    match self {
+      JrAny::JrI64(s) => JrDocument::get_content_bytes(s, 3, depth, key),
+      JrAny::JrString(s) => JrDocument::get_content_bytes(s, 2, depth, key),
+      JrAny::JrDocument(s) => JrDocument::get_content_bytes(s, 0, depth, key),
+      JrAny::JrCollection(s) => {
+        let len = s.len() as u32;
+        let mut bytes = JrDocument::get_content_bytes(s, 1, depth, key);
+        bytes.splice(7..11, len.to_be_bytes().iter().cloned());
+        bytes
+      },
+    }
+  }
+}
+
+/// Direction of a key given to `Database::sort_by`.
+#[derive(Clone, Copy)]
+pub enum SortOrder{
+  Asc,
+  Desc,
+}
+
+/// Change made to each document of an update, given to `Database::operator`.
+/// Fields are dot separated paths from the updated document.
+#[derive(Clone)]
+pub enum UpdateOp{
+  /// Add to an i64 field, a missing field is set to the amount.
+  Inc(String, i64),
+  /// Remove a field.
+  Unset(String),
+  /// Add a document at the end of a collection field, a missing field is
+  /// created with it.
+  Push(String, JrDocument),
+  /// Remove the documents of a collection field matching the condition.
+  Pull(String, JrCondition),
+  /// Move a field to another path, replacing what is there.
+  Rename(String, String),
+  /// Set a field to the value when it is smaller than the current one or the
+  /// field is missing, ordered like `Database::sort_by`.
+  Min(String, JrAny),
+  /// Set a field to the value when it is larger than the current one or the
+  /// field is missing, ordered like `Database::sort_by`.
+  Max(String, JrAny),
+}
+
+/// Value computed for each group of `Database::aggregate`, the field of the
+/// accumulators is a dot separated path.
+#[derive(Clone)]
+pub enum Accumulator{
+  /// Number of documents in the group.
+  Count,
+  /// Sum of the i64 values of the field.
+  Sum(String),
+  /// Average of the i64 values of the field, rounded toward zero.
+  Avg(String),
+  /// Smallest value of the field, ordered like `Database::sort_by`.
+  Min(String),
+  /// Largest value of the field, ordered like `Database::sort_by`.
+  Max(String),
+  /// Collection with a document holding the field for every document of the
+  /// group that has it.
+  Push(String),
+}
+
 #[derive(Clone)]
 pub enum ConditionType{
   And,
@@ -29,12 +99,56 @@ pub enum ConditionType{
   NStE,
 }
 
+/// One side of a condition expression, either a field of the document being
+/// tested or a literal value.
+#[derive(Clone)]
+pub enum JrOperand{
+  Field(String),
+  Literal(JrAny),
+}
+
+impl JrOperand{
+  pub fn field<S:Into<String>>(path:S)->Self{
+    JrOperand::Field(path.into())
+  }
+}
+
+impl From<&str> for JrOperand{
+  fn from(data: &str) -> Self {
+    JrOperand::Literal(JrAny::JrString(JrString::new(data.into())))
+  }
+}
+
+impl From<String> for JrOperand{
+  fn from(data: String) -> Self {
+    JrOperand::Literal(JrAny::JrString(JrString::new(data)))
+  }
+}
+
+impl From<i64> for JrOperand{
+  fn from(data: i64) -> Self {
+    JrOperand::Literal(JrAny::JrI64(JrI64::new(data)))
+  }
+}
+
+impl From<i32> for JrOperand{
+  fn from(data: i32) -> Self {
+    JrOperand::Literal(JrAny::JrI64(JrI64::new(data as i64)))
+  }
+}
+
+impl From<JrAny> for JrOperand{
+  fn from(data: JrAny) -> Self {
+    JrOperand::Literal(data)
+  }
+}
+
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct JrCondition{
   cond_type:ConditionType,
   conditions:Vec<JrCondition>,
-  expression:(String, String)
+  expression:(JrOperand, JrOperand)
 }
 
 impl JrCondition{
@@ -54,7 +168,7 @@ impl JrCondition{
     }
   }
 
-  pub fn new_exp(cond_type:ConditionType, conditions:Vec<JrCondition>, expression:(String, String))->Self{
+  pub fn new_exp(cond_type:ConditionType, conditions:Vec<JrCondition>, expression:(JrOperand, JrOperand))->Self{
     JrCondition{
       cond_type,
       conditions,
@@ -66,57 +180,195 @@ impl JrCondition{
     self.conditions.push(cond);
   }
 
-  pub fn compare_string(&self, val1:&String, val2:&String)->bool{
-    if let ConditionType::Eq = self.cond_type {
-      val1.eq(val2)
-    }else{
-      false
+  pub(crate) fn cond_type(&self)->&ConditionType{
+    &self.cond_type
+  }
+
+  pub(crate) fn conditions(&self)->&[JrCondition]{
+    &self.conditions
+  }
+
+  pub(crate) fn expression(&self)->&(JrOperand, JrOperand){
+    &self.expression
+  }
+
+  /// Paths of every field read by the condition.
+  pub fn fields(&self)->Vec<&str>{
+    let mut fields = vec![];
+    for elem in [&self.expression.0, &self.expression.1].iter() {
+      if let JrOperand::Field(path) = elem {
+        fields.push(path.as_str());
+      }
+    }
+    for elem in self.conditions.iter() {
+      fields.append(&mut elem.fields());
     }
+    fields
+  }
+
+  /// Map the ordering of two operands (left compared to right) to the result
+  /// of this expression without its negation, see `is_negated`.
+  fn compare_ordering(&self, ordering:Ordering)->bool{
+    match self.cond_type {
+      ConditionType::Eq | ConditionType::NEq => ordering == Ordering::Equal,
+      ConditionType::Gt | ConditionType::NGt => ordering == Ordering::Greater,
+      ConditionType::GtE | ConditionType::NGtE => ordering != Ordering::Less,
+      ConditionType::St | ConditionType::NSt => ordering == Ordering::Less,
+      ConditionType::StE | ConditionType::NStE => ordering != Ordering::Greater,
+      ConditionType::And | ConditionType::Or => false,
+    }
+  }
+
+  /// Whether the expression is true when no pair of values matches its
+  /// operator without the negation.
+  fn is_negated(&self)->bool{
+    matches!(self.cond_type,
+      ConditionType::NEq | ConditionType::NGt | ConditionType::NGtE | ConditionType::NSt | ConditionType::NStE)
+  }
+
+  pub fn compare_string(&self, val1:&str, val2:&str)->bool{
+    self.compare_ordering(val1.cmp(val2))
   }
 
   fn compare_i64(&self, val1:i64, val2:i64)->bool{
-    if let ConditionType::Eq = self.cond_type {
-      val1==val2
-    }else{
-      false
+    self.compare_ordering(val1.cmp(&val2))
+  }
+
+  /// Compare an i64 with a string. A string holding a number is compared
+  /// numerically, anything else falls back to comparing the string forms.
+  fn compare_mixed(&self, val1:&JrAny, val2:&JrAny)->bool{
+    let num1 = self.get_as_i64(val1);
+    let num2 = self.get_as_i64(val2);
+    if let (Some(v1), Some(v2)) = (num1, num2) {
+      return self.compare_i64(v1, v2);
+    }
+
+    match (self.get_as_string(val1), self.get_as_string(val2)) {
+      (Some(s1), Some(s2)) => self.compare_string(&s1, &s2),
+      _ => false,
     }
   }
 
-  fn get_as_string(&self, val:JrAny)->Option<String>{
-    if let JrAny::JrString(s) = val{
-      Some(s.get().clone())
-    }else if let JrAny::JrI64(s) = val{
-      Some(s.get().to_string())
-    }else{
-      None
+  fn get_as_i64(&self, val:&JrAny)->Option<i64>{
+    match val {
+      JrAny::JrI64(v) => Some(*v.get()),
+      JrAny::JrString(s) => s.get().parse::<i64>().ok(),
+      _ => None,
     }
   }
 
-  fn get_value(&self, value:&String, doc:&JrDocument)->Option<JrAny>{
-    //horrible code, need fix (use regex)
-    let mut r_chars = value.chars();
-    let r_char1 = r_chars.nth(0).unwrap();
-    let r_char2 = r_chars.nth(value.len()-2).unwrap();
+  fn get_as_string(&self, val:&JrAny)->Option<String>{
+    match val {
+      JrAny::JrString(s) => Some(s.get().clone()),
+      JrAny::JrI64(v) => Some(v.get().to_string()),
+      _ => None,
+    }
+  }
 
-    if r_char1.eq(&'\'') && r_char1.eq(&r_char2) {
-      let mut val2:&str = &self.expression.1.clone();
-      val2 = &val2[1..val2.len()];
-      val2 = &val2[0..val2.len()-1];
-      Some(JrAny::JrString(JrString::new(val2.into())))
-    //assume is number
-    }else if value.parse::<i64>().is_ok(){
-      Some(JrAny::JrI64(JrI64::new(value.parse::<i64>().unwrap())))
-    }else{
-      if let Ok(v) = doc.get_value(&value){
-        Some(JrAny::JrI64(JrI64::new(v)))
-      }else if let Ok(v) = doc.get_value(&value){
-        Some(JrAny::JrString(JrString::new(v)))
-      }else{
-        None
-      }
+  fn get_values<'a>(&'a self, value:&'a JrOperand, doc:&'a JrDocument)->Vec<&'a JrAny>{
+    match value {
+      JrOperand::Field(key) => doc.get_path_all(key),
+      JrOperand::Literal(data) => vec![data],
     }
   }
 
+  fn compare(&self, val1:&JrAny, val2:&JrAny)->bool{
+    match (val1, val2) {
+      (JrAny::JrString(s1), JrAny::JrString(s2)) => {
+        self.compare_string(s1.get(), s2.get())
+      },
+      (JrAny::JrI64(v1), JrAny::JrI64(v2)) => {
+        self.compare_i64(*v1.get(), *v2.get())
+      },
+      (left, right) => self.compare_mixed(left, right),
+    }
+  }
+
+  /// Evaluate the condition against a document.
+  ///
+  /// The left operand of `exp!` is a field of the document and the right
+  /// operand is a literal, use `JrOperand::field` to compare two fields.
+  /// A field going through a collection, like `tags.name`, matches when any
+  /// item of the collection matches, a negated operator like `!=` matches
+  /// when no item matches the operator it negates. A missing field matches
+  /// the negated operators only.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument, JrOperand };
+  /// use jrdb::exp;
+  ///
+  /// let mut doc = JrDocument::new();
+  /// doc.add_value("name", String::from("Joel"));
+  /// doc.add_value("age", 30);
+  /// doc.add_value("level", String::from("12"));
+  /// doc.add_value("'x'", String::from("quoted"));
+  ///
+  /// //i64 operands
+  /// assert!(exp!{"age" ;== 30}.result(&doc));
+  /// assert!(exp!{"age" ;!= 18}.result(&doc));
+  /// assert!(exp!{"age" ;> 18}.result(&doc));
+  /// assert!(!exp!{"age" ;!> 18}.result(&doc));
+  /// assert!(exp!{"age" ;>= 30}.result(&doc));
+  /// assert!(!exp!{"age" ;!>= 30}.result(&doc));
+  /// assert!(exp!{"age" ;< 31}.result(&doc));
+  /// assert!(exp!{"age" ;!< 30}.result(&doc));
+  /// assert!(exp!{"age" ;<= 30}.result(&doc));
+  /// assert!(!exp!{"age" ;!<= 30}.result(&doc));
+  ///
+  /// //String operands
+  /// assert!(exp!{"name" ;== "Joel"}.result(&doc));
+  /// assert!(exp!{"name" ;!= "Mathew"}.result(&doc));
+  /// assert!(exp!{"name" ;> "Jason"}.result(&doc));
+  /// assert!(exp!{"name" ;!> "Joel"}.result(&doc));
+  /// assert!(exp!{"name" ;>= "Joel"}.result(&doc));
+  /// assert!(exp!{"name" ;!>= "Mathew"}.result(&doc));
+  /// assert!(exp!{"name" ;< "Mathew"}.result(&doc));
+  /// assert!(exp!{"name" ;!< "Jason"}.result(&doc));
+  /// assert!(exp!{"name" ;<= "Joel"}.result(&doc));
+  /// assert!(exp!{"name" ;!<= "Jason"}.result(&doc));
+  ///
+  /// //mixed operands, numeric strings are compared as numbers
+  /// assert!(exp!{"level" ;< 100}.result(&doc));
+  /// assert!(exp!{"level" ;== "12"}.result(&doc));
+  /// assert!(exp!{"age" ;> "18"}.result(&doc));
+  /// assert!(!exp!{"name" ;== 30}.result(&doc));
+  /// assert!(exp!{"name" ;> 30}.result(&doc));
+  ///
+  /// //field to field
+  /// assert!(exp!{"age" ;> JrOperand::field("level")}.result(&doc));
+  /// assert!(!exp!{"age" ;== "level"}.result(&doc));
+  ///
+  /// //any item of a collection
+  /// let mut tag = JrDocument::new();
+  /// tag.add_value("name", String::from("rust"));
+  /// let mut tags = JrCollection::new();
+  /// tags.add(tag);
+  /// doc.add_value("tags", tags);
+  /// assert!(exp!{"tags.name" ;== "rust"}.result(&doc));
+  /// assert!(!exp!{"tags.name" ;== "go"}.result(&doc));
+  /// assert!(exp!{"tags.name" ;!= "go"}.result(&doc));
+  ///
+  /// //no item of a collection for the negated operators
+  /// let mut tag = JrDocument::new();
+  /// tag.add_value("name", String::from("x"));
+  /// let mut other = JrDocument::new();
+  /// other.add_value("name", String::from("y"));
+  /// let mut tags = JrCollection::new();
+  /// tags.add(tag);
+  /// tags.add(other);
+  /// doc.add_value("labels", tags);
+  /// assert!(!exp!{"labels.name" ;!= "x"}.result(&doc));
+  /// assert!(exp!{"labels.name" ;!= "z"}.result(&doc));
+  /// assert!(!exp!{"labels.name" ;!> "x"}.result(&doc));
+  /// assert!(exp!{"labels.name" ;!< "x"}.result(&doc));
+  /// assert!(exp!{"missing" ;!= "x"}.result(&doc));
+  /// assert!(!exp!{"missing" ;== "x"}.result(&doc));
+  ///
+  /// //quotes are never parsed out of a field name or a literal
+  /// assert!(exp!{"'x'" ;== "quoted"}.result(&doc));
+  /// assert!(!exp!{"name" ;== "'Joel'"}.result(&doc));
+  /// ```
   pub fn result(&self, doc:&JrDocument)->bool{
     if let ConditionType::And = self.cond_type {
       let mut data = true;
@@ -137,32 +389,10 @@ impl JrCondition{
       }
       data
     }else{
-      let mut result = false;
-
-      let val1 = self.get_value(&self.expression.0, doc);
-      let val2 = self.get_value(&self.expression.1, doc);
-
-      if val1.is_some() && val2.is_some() {
-        let left = val1.unwrap();
-        let right = val2.unwrap();
-
-        if let JrAny::JrString(s1) = left{
-          if let Some(s2) = self.get_as_string(right){
-            result = self.compare_string(s1.get() , &s2);
-          }
-        }else if let JrAny::JrString(s1) = right{
-          if let Some(s2) = self.get_as_string(left){
-            result = self.compare_string(s1.get() , &s2);
-          }
-        }else if let JrAny::JrI64(v1) = left {
-          if let JrAny::JrI64(v2) = right {
-            result = self.compare_i64(*v1.get(), *v2.get());
-          }
-        }
-      }else{
-        result = false;
-      }
-      result
+      let val1 = self.get_values(&self.expression.0, doc);
+      let val2 = self.get_values(&self.expression.1, doc);
+      let any = val1.iter().any(|left| val2.iter().any(|right| self.compare(left, right)));
+      any != self.is_negated()
     }
   }
 }
@@ -178,7 +408,7 @@ pub trait AddGetValue<T>
 {
   fn add_value(&mut self, key:&str, item: T);
   fn get_value(&self, key:&str)->Result<T, &str>;
-  fn get_value_from_db(&mut self, db:&mut Database, header:&mut HeaderDetail)->T;
+  fn get_value_from_db(&mut self, db:&mut Database, header:&mut HeaderDetail)->Result<T, JrError>;
 }
 
 pub trait JrType{
@@ -187,18 +417,18 @@ pub trait JrType{
   fn new_attr_header(data_type:u8, depth:u8, content_size:u32, name:String)->Vec<u8>{
     let mut header:Vec<u8> = [depth,data_type,0].to_vec();
     
-    let mut key = String::from(name).into_bytes();
+    let mut key = name.into_bytes();
 
     let key_len = key.len() as u8;
     header[2] = key_len;
     let attr_size = if data_type==1 {
-      11+key_len
+      11+key_len as u32
     }else{
-      7+key_len
-    } as u32;
+      7+key_len as u32
+    };
 
     let mut attr_size_bytes:Vec<u8> = Vec::new();
-    let slice_u32: &[u32] = &*vec![attr_size + content_size];
+    let slice_u32: &[u32] = &[attr_size + content_size];
     for &n in slice_u32 {
       let _ = attr_size_bytes.write_u32::<BigEndian>(n);
     }
@@ -251,7 +481,7 @@ impl From<&JrI64> for i64{
 impl JrType for JrI64{
   fn get_bytes(&mut self, _:u8)->Vec<u8>{
     let mut data:Vec<u8> = Vec::new();
-    let slice_i64: &[i64] = &*vec![self.data];
+    let slice_i64: &[i64] = &[self.data];
     for &n in slice_i64 {
       let _ = data.write_i64::<BigEndian>(n);
     }
@@ -299,7 +529,7 @@ impl JrType for JrString{
   }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct JrCollection{
   data:Vec<JrDocument>
 }
@@ -326,6 +556,10 @@ impl JrCollection{
     self.data.len()
   }
 
+  pub fn is_empty(&self)->bool{
+    self.data.is_empty()
+  }
+
   pub fn print(&self, depth:u8){
     let space = "  ".repeat(depth as usize);
     for elem in self.data.iter() {
@@ -336,7 +570,7 @@ impl JrCollection{
       elem.print(depth+1);
     }
 
-    println!("");
+    println!();
   }
 }
 
@@ -345,7 +579,7 @@ impl JrType for JrCollection{
     let mut data = Vec::new();
     for (i, jr_doc) in self.data.iter_mut().enumerate() {
       let mut content_bytes = jr_doc.get_bytes(depth+1);
-      let mut header = JrCollection::new_attr_header(2, depth+1,content_bytes.len() as u32, i.to_string());
+      let mut header = JrCollection::new_attr_header(0, depth+1,content_bytes.len() as u32, i.to_string());
       header.append(&mut content_bytes);
       data.append(&mut header);
     }
@@ -353,7 +587,7 @@ impl JrType for JrCollection{
   }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct JrDocument{
     data:BTreeMap<String, JrAny>,
 }
@@ -382,17 +616,140 @@ impl JrDocument{
           println!("{}{}: {}",space,key,s.get());
         }else if let JrAny::JrString(s) = elem.1{
           println!("{}{}: {}",space,key,s.get());
+        }else if let JrAny::JrDocument(s) = elem.1{
+          println!("{}{}:",space,key);
+          s.print(depth+1);
+        }else if let JrAny::JrCollection(s) = elem.1{
+          println!("{}{}:",space,key);
+          s.print(depth+1);
         }
       }
     }
-    println!("");
+    println!();
+  }
+
+  /// Make sure every key, including keys of nested documents, fits in an
+  /// attribute header. Dot separated keys are checked per path segment.
+  pub fn check_keys(&self)->Result<(), JrError>{
+    for (key, data) in self.data.iter() {
+      for segment in key.split('.') {
+        check_key(segment)?;
+      }
+      match data {
+        JrAny::JrDocument(doc) => doc.check_keys()?,
+        JrAny::JrCollection(collec) => {
+          for doc in collec.data.iter() {
+            doc.check_keys()?;
+          }
+        },
+        _ => {},
+      }
+    }
+    Ok(())
+  }
+
+  pub fn get_any(&self, key:&str)->Option<&JrAny>{
+    self.data.get(key)
+  }
+
+  pub fn add_any(&mut self, key:&str, item:JrAny){
+    self.data.insert(key.to_string(), item);
+  }
+
+  pub fn remove(&mut self, key:&str)->Option<JrAny>{
+    self.data.remove(key)
+  }
+
+  /// Remove a value by a dot separated path, items of a collection are
+  /// addressed by their position like `get_path`.
+  pub fn remove_path(&mut self, path:&str)->Option<JrAny>{
+    match path.split_once('.') {
+      None => self.data.remove(path),
+      Some((key, rest)) => match self.data.get_mut(key) {
+        Some(JrAny::JrDocument(doc)) => doc.remove_path(rest),
+        Some(JrAny::JrCollection(collec)) => {
+          let (pos, rest) = rest.split_once('.')?;
+          let pos = pos.parse::<usize>().ok()?;
+          collec.data.get_mut(pos)?.remove_path(rest)
+        },
+        _ => None,
+      },
+    }
+  }
+
+  /// Get a value by a dot separated path such as `address.city`, items of a
+  /// collection are addressed by their position, e.g. `tags.0.name`.
+  pub fn get_path(&self, path:&str)->Option<&JrAny>{
+    match path.split_once('.') {
+      None => self.data.get(path),
+      Some((key, rest)) => match self.data.get(key) {
+        Some(JrAny::JrDocument(doc)) => doc.get_path(rest),
+        Some(JrAny::JrCollection(collec)) => {
+          let (pos, rest) = match rest.split_once('.') {
+            Some((pos, rest)) => (pos, rest),
+            None => return None,
+          };
+          let pos = pos.parse::<usize>().ok()?;
+          collec.data.get(pos)?.get_path(rest)
+        },
+        _ => None,
+      },
+    }
+  }
+
+  /// Get every value at a dot separated path. A collection followed by a key
+  /// instead of a position continues the path into each of its items, so
+  /// `tags.name` gives the name of every tag.
+  pub fn get_path_all(&self, path:&str)->Vec<&JrAny>{
+    match path.split_once('.') {
+      None => self.data.get(path).into_iter().collect(),
+      Some((key, rest)) => match self.data.get(key) {
+        Some(JrAny::JrDocument(doc)) => doc.get_path_all(rest),
+        Some(JrAny::JrCollection(collec)) => {
+          let (pos, tail) = rest.split_once('.').unwrap_or((rest, ""));
+          match pos.parse::<usize>() {
+            Ok(pos) if !tail.is_empty() => collec.data.get(pos).map(|doc| doc.get_path_all(tail)).unwrap_or_default(),
+            Ok(_) => vec![],
+            Err(_) => collec.data.iter().flat_map(|doc| doc.get_path_all(rest)).collect(),
+          }
+        },
+        _ => vec![],
+      },
+    }
+  }
+
+  /// Set a value by a dot separated path, missing documents along the path
+  /// are created. Items of a collection are only updated when they exist.
+  pub fn set_path(&mut self, path:&str, item:JrAny){
+    match path.split_once('.') {
+      None => {
+        self.data.insert(path.to_string(), item);
+      },
+      Some((key, rest)) => match self.data.get_mut(key) {
+        Some(JrAny::JrDocument(doc)) => doc.set_path(rest, item),
+        Some(JrAny::JrCollection(collec)) => {
+          if let Some((pos, rest)) = rest.split_once('.') {
+            let doc = pos.parse::<usize>().ok().and_then(|pos| collec.data.get_mut(pos));
+            if let Some(doc) = doc {
+              doc.set_path(rest, item);
+            }
+          }
+        },
+        Some(_) => {},
+        None => {
+          let mut doc = JrDocument::new();
+          doc.set_path(rest, item);
+          self.data.insert(key.to_string(), JrAny::JrDocument(doc));
+        },
+      },
+    }
   }
 
   pub fn loop_key<F>(&mut self, f:&mut F)
   where F:FnMut(&str, &mut JrAny)
   {
     for elem in self.data.iter_mut() {
-      f(&elem.0, elem.1);
+      f(elem.0, elem.1);
     }
   }
 } 
@@ -401,29 +758,8 @@ impl JrType for JrDocument{
   fn get_bytes(&mut self, depth:u8)->Vec<u8>{
     let mut data = Vec::new();
     for elem in self.data.iter_mut() {
-      let content = elem.1;
-      let mut content_bytes = if let JrAny::JrI64(s) = content {
-
-        JrDocument::get_content_bytes(s, 3, depth+1, String::from(elem.0))
-
-      } else if let JrAny::JrString(s) = content {
-
-        JrDocument::get_content_bytes(s, 2, depth+1, String::from(elem.0))
-
-      } else if let JrAny::JrCollection(s) = content{
-
-        JrDocument::get_content_bytes(s, 1, depth+1, String::from(elem.0))
-
-      } else if let JrAny::JrDocument(s) = content{
-
-        JrDocument::get_content_bytes(s, 0, depth+1, String::from(elem.0))
-
-      } else {
-        Vec::new()
-      };
-
+      let mut content_bytes = elem.1.get_attr_bytes(String::from(elem.0), depth+1);
       data.append(&mut content_bytes);
-      
     }
     data
   }
@@ -434,7 +770,7 @@ impl AddGet<JrDocument> for JrDocument{
     self.data.insert(key.to_string(), JrAny::JrDocument(item));
   }
   fn get(&self, key:&str)->Result<&JrDocument, &str>{
-    if let None = self.data.get(key){
+    if !self.data.contains_key(key){
       Err("Key not found")
     }else if let JrAny::JrDocument(s) = self.data.get(key).unwrap(){
       Ok(s)
@@ -450,7 +786,7 @@ impl AddGet<JrCollection> for JrDocument{
   }
 
   fn get(&self, key:&str)->Result<&JrCollection, &str>{
-    if let None = self.data.get(key){
+    if !self.data.contains_key(key){
       Err("Key not found")
     }else if let JrAny::JrCollection(s) = self.data.get(key).unwrap(){
       Ok(s)
@@ -466,7 +802,7 @@ impl AddGet<JrString> for JrDocument{
     self.data.insert(key.to_string(), JrAny::JrString(item));
   }
   fn get(&self, key:&str)->Result<&JrString, &str>{
-    if let None = self.data.get(key){
+    if !self.data.contains_key(key){
       Err("Key not found")
     }else if let JrAny::JrString(s) = self.data.get(key).unwrap(){
       Ok(s)
@@ -481,7 +817,7 @@ impl AddGet<JrI64> for JrDocument{
     self.data.insert(key.to_string(), JrAny::JrI64(item));
   }
   fn get(&self, key:&str)->Result<&JrI64, &str>{
-    if let None = self.data.get(key){
+    if !self.data.contains_key(key){
       Err("Key not found")
     }else if let JrAny::JrI64(s) = self.data.get(key).unwrap(){
       Ok(s)
@@ -498,7 +834,7 @@ impl AddGetValue<String> for JrDocument{
   }
 
   fn get_value(&self, key:&str)->Result<String, &str>{
-    if let None = self.data.get(key){
+    if !self.data.contains_key(key){
       Err("Key not found")
     }else if let JrAny::JrString(s) = self.data.get(key).unwrap(){
       Ok(s.get().clone())
@@ -507,13 +843,11 @@ impl AddGetValue<String> for JrDocument{
     }   
   }
 
-  fn get_value_from_db(&mut self, db:&mut Database, header:&mut HeaderDetail)->String{
-    let data = match String::from_utf8(db.get_bytes_content(header)){
-      Ok(d)=>d,
-      Err(_)=>String::from(""),
-    };
+  fn get_value_from_db(&mut self, db:&mut Database, header:&mut HeaderDetail)->Result<String, JrError>{
+    let data = String::from_utf8(db.get_bytes_content(header)?)
+      .map_err(|_| JrError::Corrupt{ offset:header.header_start })?;
     self.add_value(&header.key, data.clone());
-    data
+    Ok(data)
   }
 }
 
@@ -524,7 +858,7 @@ impl AddGetValue<i64> for JrDocument{
   }
 
   fn get_value(&self, key:&str)->Result<i64, &str>{
-    if let None = self.data.get(key){
+    if !self.data.contains_key(key){
       Err("Key not found")
     }else if let JrAny::JrI64(s) = self.data.get(key).unwrap(){
       Ok( *s.get() )
@@ -533,8 +867,11 @@ impl AddGetValue<i64> for JrDocument{
     }   
   }
 
-  fn get_value_from_db(&mut self, db:&mut Database, header:&mut HeaderDetail)->i64{
-    let data = db.get_bytes_content(header);
+  fn get_value_from_db(&mut self, db:&mut Database, header:&mut HeaderDetail)->Result<i64, JrError>{
+    let data = db.get_bytes_content(header)?;
+    if data.len() != 8 {
+      return Err(JrError::Corrupt{ offset:header.header_start });
+    }
 
     let mut arr:[u8;8] = [0;8];
     for (i,item) in data.iter().enumerate() {
@@ -542,6 +879,56 @@ impl AddGetValue<i64> for JrDocument{
     }
     
     self.add_value(&header.key, i64::from_be_bytes(arr));
-    i64::from_be_bytes(arr)
+    Ok(i64::from_be_bytes(arr))
+  }
+}
+
+impl AddGetValue<JrDocument> for JrDocument{
+  fn add_value(&mut self, key:&str, item: JrDocument){
+    self.data.insert(key.to_string(), JrAny::JrDocument(item));
+  }
+
+  fn get_value(&self, key:&str)->Result<JrDocument, &str>{
+    if !self.data.contains_key(key){
+      Err("Key not found")
+    }else if let JrAny::JrDocument(s) = self.data.get(key).unwrap(){
+      Ok(s.clone())
+    }else{
+      Err("Not a JrDocument")
+    }
+  }
+
+  fn get_value_from_db(&mut self, db:&mut Database, header:&mut HeaderDetail)->Result<JrDocument, JrError>{
+    let data = db.get_doc_by_header(header)?;
+    self.add_value(&header.key, data.clone());
+    Ok(data)
+  }
+}
+
+impl AddGetValue<JrCollection> for JrDocument{
+  fn add_value(&mut self, key:&str, item: JrCollection){
+    self.data.insert(key.to_string(), JrAny::JrCollection(item));
+  }
+
+  fn get_value(&self, key:&str)->Result<JrCollection, &str>{
+    if !self.data.contains_key(key){
+      Err("Key not found")
+    }else if let JrAny::JrCollection(s) = self.data.get(key).unwrap(){
+      Ok(s.clone())
+    }else{
+      Err("Not a JrCollection")
+    }
+  }
+
+  fn get_value_from_db(&mut self, db:&mut Database, header:&mut HeaderDetail)->Result<JrCollection, JrError>{
+    let mut data = JrCollection::new();
+    let mut curr_pos = header.content_start;
+    while curr_pos < header.content_end {
+      let item_header = db.get_child_header_by_pos(header, curr_pos)?;
+      data.add(db.get_doc_by_header(&item_header)?);
+      curr_pos += item_header.content_size;
+    }
+    self.add_value(&header.key, data.clone());
+    Ok(data)
   }
 }
\ No newline at end of file
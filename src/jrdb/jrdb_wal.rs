@@ -0,0 +1,194 @@
+use std::fs::{ File, OpenOptions };
+use std::io::{ ErrorKind, Read, Seek, SeekFrom, Write };
+use std::path::{ Path, PathBuf };
+use super::jrdb_error::JrError;
+use super::jrdb_file::sync_dir;
+
+/// Every write-ahead log starts with these bytes.
+const WAL_MAGIC:[u8;4] = *b"JRWL";
+
+const WAL_VERSION:u16 = 1;
+
+/// Magic bytes, version (u16) and the checkpoint sequence (u64) of the
+/// database file the log applies to.
+const WAL_HEADER_LEN:u64 = 14;
+
+/// Frame length (u32) and checksum (u32) written before each frame.
+const FRAME_HEADER_LEN:usize = 8;
+
+/// The log is checkpointed into the database file once it grows past this.
+pub const CHECKPOINT_SIZE:u64 = 1 << 20;
+
+/// Replace the bytes `start..end` of the database with `data`. Every change
+/// to the database is made of these, so replaying them in order rebuilds it.
+pub struct WalSplice{
+  pub start:usize,
+  pub end:usize,
+  pub data:Vec<u8>,
+}
+
+/// Write-ahead log kept next to the `.db` file. Each commit appends one frame
+/// holding the splices of the committed actions, a frame is only replayed
+/// when it was completely written.
+pub struct Wal{
+  file:File,
+  len:u64,
+}
+
+impl Wal{
+  pub fn path(db_path:&Path)->PathBuf{
+    db_path.with_extension("wal")
+  }
+
+  /// Open the log of the database at `checkpoint` and return the frames that
+  /// have not been checkpointed yet. A log left by another checkpoint or with
+  /// a torn last frame is cut back to what can be replayed.
+  pub fn open(path:&Path, checkpoint:u64)->Result<(Wal, Vec<Vec<WalSplice>>), JrError>{
+    let mut file = OpenOptions::new()
+    .read(true)
+    .write(true)
+    .truncate(false)
+    .create(true).open(path)?;
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+
+    let mut wal = Wal{
+      file,
+      len:data.len() as u64,
+    };
+
+    if !wal.is_log_of(&data, checkpoint) {
+      wal.reset(checkpoint)?;
+      //the log may have just been created, its directory entry has to be
+      //durable before a commit is
+      sync_dir(path)?;
+      return Ok((wal, vec![]));
+    }
+
+    let mut frames = vec![];
+    let mut pos = WAL_HEADER_LEN as usize;
+    while let Some((frame, frame_end)) = read_frame(&data, pos) {
+      frames.push(frame);
+      pos = frame_end;
+    }
+
+    if pos != data.len() {
+      wal.file.set_len(pos as u64)?;
+      wal.file.sync_all()?;
+      wal.len = pos as u64;
+    }
+
+    Ok((wal, frames))
+  }
+
+  fn is_log_of(&self, data:&[u8], checkpoint:u64)->bool{
+    if data.len() < WAL_HEADER_LEN as usize || !data.starts_with(&WAL_MAGIC) {
+      return false;
+    }
+    let mut version = [0;2];
+    version.copy_from_slice(&data[4..6]);
+    let mut sequence = [0;8];
+    sequence.copy_from_slice(&data[6..14]);
+    u16::from_be_bytes(version) == WAL_VERSION && u64::from_be_bytes(sequence) == checkpoint
+  }
+
+  pub fn len(&self)->u64{
+    self.len
+  }
+
+  /// Append one frame and sync it, the splices are durable once this returns.
+  pub fn append(&mut self, splices:&[WalSplice])->Result<(), JrError>{
+    let mut payload = Vec::new();
+    for splice in splices.iter() {
+      payload.extend_from_slice(&(splice.start as u32).to_be_bytes());
+      payload.extend_from_slice(&(splice.end as u32).to_be_bytes());
+      payload.extend_from_slice(&(splice.data.len() as u32).to_be_bytes());
+      payload.extend_from_slice(&splice.data);
+    }
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&crc32(&payload).to_be_bytes());
+    frame.append(&mut payload);
+
+    self.file.seek(SeekFrom::Start(self.len))?;
+    self.file.write_all(&frame)?;
+    self.file.sync_data()?;
+    self.len += frame.len() as u64;
+    Ok(())
+  }
+
+  /// Empty the log after the database file reached `checkpoint`.
+  pub fn reset(&mut self, checkpoint:u64)->Result<(), JrError>{
+    let mut header = WAL_MAGIC.to_vec();
+    header.extend_from_slice(&WAL_VERSION.to_be_bytes());
+    header.extend_from_slice(&checkpoint.to_be_bytes());
+
+    self.file.set_len(0)?;
+    self.file.seek(SeekFrom::Start(0))?;
+    self.file.write_all(&header)?;
+    self.file.sync_all()?;
+    self.len = WAL_HEADER_LEN;
+    Ok(())
+  }
+}
+
+/// Read the frame at `pos`, returns `None` for a torn or damaged frame.
+fn read_frame(data:&[u8], pos:usize)->Option<(Vec<WalSplice>, usize)>{
+  let header = data.get(pos..pos+FRAME_HEADER_LEN)?;
+  let payload_len = read_u32(header, 0)? as usize;
+  let checksum = read_u32(header, 4)?;
+  let payload_start = pos+FRAME_HEADER_LEN;
+  let payload = data.get(payload_start..payload_start+payload_len)?;
+  if crc32(payload) != checksum {
+    return None;
+  }
+
+  let mut splices = vec![];
+  let mut curr_pos = 0;
+  while curr_pos < payload.len() {
+    let start = read_u32(payload, curr_pos)? as usize;
+    let end = read_u32(payload, curr_pos+4)? as usize;
+    let data_len = read_u32(payload, curr_pos+8)? as usize;
+    let data_start = curr_pos+12;
+    let data = payload.get(data_start..data_start+data_len)?.to_vec();
+    splices.push(WalSplice{
+      start,
+      end,
+      data,
+    });
+    curr_pos = data_start+data_len;
+  }
+
+  Some((splices, payload_start+payload_len))
+}
+
+fn read_u32(data:&[u8], pos:usize)->Option<u32>{
+  let mut arr = [0;4];
+  arr.copy_from_slice(data.get(pos..pos+4)?);
+  Some(u32::from_be_bytes(arr))
+}
+
+/// CRC-32 (IEEE) of `data`, used to detect a frame that was not completely
+/// written before a crash.
+fn crc32(data:&[u8])->u32{
+  let mut crc = 0xffff_ffffu32;
+  for byte in data.iter() {
+    crc ^= *byte as u32;
+    for _ in 0..8 {
+      let mask = (!(crc & 1)).wrapping_add(1);
+      crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+    }
+  }
+  !crc
+}
+
+/// Remove the log of a database, used when the database file is missing.
+pub fn remove(path:&Path)->Result<(), JrError>{
+  match std::fs::remove_file(path) {
+    Ok(_) => Ok(()),
+    Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+    Err(e) => Err(e.into()),
+  }
+}
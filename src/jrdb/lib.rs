@@ -1,30 +1,62 @@
-use std::fs::OpenOptions;
-use std::fs::File;
+use std::cmp::Ordering;
+use std::collections::{ BTreeMap, BTreeSet };
 use std::fs;
 use std::mem;
-use std::io::{Read, Write};
+use std::io::ErrorKind;
+use std::path::Path;
 use byteorder::{WriteBytesExt, BigEndian};
 pub mod jrdb_type;
+pub mod jrdb_error;
+pub mod jrdb_cursor;
+pub mod jrdb_plan;
+pub mod jrdb_result;
+pub mod jrdb_stats;
+mod jrdb_aggregate;
+mod jrdb_file;
+mod jrdb_index;
+mod jrdb_wal;
+use jrdb_error::JrError;
+use jrdb_cursor::Cursor;
+use jrdb_file::{ FileHeader, FILE_HEADER_LEN, FLAGS_OFFSET, FLAG_INDEXES, FORMAT_VERSION };
+use jrdb_index::{ Index, INDEXES_KEY };
+use jrdb_plan::{ QueryPlan, Scan };
+use jrdb_result::ExecuteResult;
+use jrdb_stats::CollectionStats;
+use jrdb_wal::{ Wal, WalSplice, CHECKPOINT_SIZE };
 use jrdb_type::{
   JrDocument, 
   JrCollection, 
   JrString, 
+  JrI64,
   JrType, 
   JrAny, 
   AddGet,
   JrCondition,
+  JrOperand,
+  ConditionType,
   AddGetValue,
+  SortOrder,
+  Accumulator,
+  UpdateOp,
 };
 
+#[allow(clippy::module_inception)]
 pub mod macros;
 
 #[allow(dead_code)]
+#[derive(Clone)]
 enum ActionType{
   Insert,
   Select,
   Update,
   UpdateForce,
+  Replace,
   Delete,
+  Aggregate,
+  CreateIndex,
+  CreateUniqueIndex,
+  DropCollection,
+  RenameCollection,
 }
 
 pub struct HeaderDetail{
@@ -34,28 +66,154 @@ pub struct HeaderDetail{
   content_start:usize,
   content_end:usize,
   content_size:usize,
+  //for a collection, the last id given to an inserted document, it is never
+  //decreased so ids are not reused after delete
   content_length:usize,
   content_type:u8,
   depth:u8,
 }
 
 #[allow(dead_code)]
+#[derive(Clone)]
 struct Action{
   action_type:ActionType,
   from:String,
   keys:Vec<String>,
+  exclude:Vec<String>,
+  //only the document with this _id is used by the action
+  id:Option<String>,
   condition:JrCondition,
+  sort:Vec<(String, SortOrder)>,
+  limit:Option<usize>,
+  skip:usize,
+  //select starts after the document with this _id
+  after:Option<String>,
+  group:Vec<String>,
+  accumulators:Vec<(String, Accumulator)>,
+  operators:Vec<UpdateOp>,
+  //an upsert sets the fields its condition compares with == when inserting
+  merge_condition:bool,
+  //documents before and after the action are kept in its result
+  returning:bool,
   data:Vec<JrDocument>
 }
 
+impl Action{
+  fn new(action_type:ActionType, from:&str)->Self{
+    Action{
+      action_type,
+      from:from.into(),
+      keys:vec![],
+      exclude:vec![],
+      id:None,
+      condition:cond_true!(),
+      sort:vec![],
+      limit:None,
+      skip:0,
+      after:None,
+      group:vec![],
+      accumulators:vec![],
+      operators:vec![],
+      merge_condition:false,
+      returning:false,
+      data:vec![]
+    }
+  }
+}
+
+/// Fields kept by a select, from `keys`, `exclude` and the path given after
+/// the collection name.
+struct Projection{
+  include:Vec<String>,
+  exclude:Vec<String>,
+  //top level keys read by the condition or the sort, they are decoded even
+  //when the projection drops them
+  read_keys:Vec<String>,
+}
+
+impl Projection{
+  fn new(action:&Action)->Self{
+    let mut include = action.keys.clone();
+    if let Some((_, path)) = action.from.split_once('.') {
+      include.push(path.into());
+    }
+    let mut read_keys:Vec<String> = action.condition.fields().iter().map(|path| Projection::top_key(path).into()).collect();
+    for (path, _) in action.sort.iter() {
+      read_keys.push(Projection::top_key(path).into());
+    }
+    Projection{
+      include,
+      exclude:action.exclude.clone(),
+      read_keys,
+    }
+  }
+
+  fn top_key(path:&str)->&str{
+    path.split('.').next().unwrap_or(path)
+  }
+
+  /// Whether the top level `key` of a document has to be decoded.
+  fn decodes(&self, key:&str)->bool{
+    if self.read_keys.iter().any(|k| k == key) {
+      return true;
+    }
+    if !self.include.is_empty() {
+      return self.include.iter().any(|path| Projection::top_key(path) == key);
+    }
+    !self.exclude.iter().any(|path| path == key)
+  }
+
+  /// Keep `_id` and the included paths, then drop the excluded paths.
+  fn apply(&self, jr_doc:JrDocument)->JrDocument{
+    let mut jr_doc = if self.include.is_empty() {
+      jr_doc
+    }else{
+      let mut projected = JrDocument::new();
+      if let Some(id) = jr_doc.get_any("_id") {
+        projected.set_path("_id", id.clone());
+      }
+      for path in self.include.iter() {
+        if let Some(data) = jr_doc.get_path(path) {
+          projected.set_path(path, data.clone());
+        }
+      }
+      projected
+    };
+    for path in self.exclude.iter() {
+      jr_doc.remove_path(path);
+    }
+    jr_doc
+  }
+}
+
 
 pub struct Database{
-  _file:File,
   data:Vec<u8>,
   file_name:String,
-  actions:Vec<Action>
+  actions:Vec<Action>,
+  wal:Wal,
+  //changes made to data since the last commit
+  pending:Vec<WalSplice>,
+  //bytes replaced by each of the pending changes, to undo a failed execute
+  replaced:Vec<Vec<u8>>,
+  //in-memory copy of the indexes stored in the data
+  indexes:Vec<Index>,
+  //data and indexes as of the last commit, kept while a transaction is open
+  transaction:Option<(Vec<u8>, Vec<Index>)>
+}
+
+/// Longest key or collection name that fits in an attribute header.
+const MAX_KEY_LEN:usize = 255;
+
+fn check_key(key:&str)->Result<(), JrError>{
+  if key.len() > MAX_KEY_LEN {
+    Err(JrError::KeyTooLong(key.into()))
+  }else{
+    Ok(())
+  }
 }
 
+#[allow(dead_code)]
 trait GenericDatabaseFeature<T>{
   fn insert(&self, from:&str, key:&str, data:T);
 }
@@ -71,54 +229,453 @@ impl Database{
   /// 
   /// fn main() {
   ///   //create "main.db" if file not exist
-  ///   let db:Database = Database::from("main");
+  ///   let db:Database = Database::from("main").unwrap();
   /// }
   /// ```
-  pub fn from(s:&str)->Database{
-      
-    if let Ok(db_file) = OpenOptions::new().read(true).write(true).open(format!("{}.db",s)){
+  ///
+  /// A file that is not a valid database returns an error instead of panicking.
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  ///
+  /// //not a database file
+  /// std::fs::write("doc_foreign.db", "hello world").unwrap();
+  /// assert!(matches!(Database::from("doc_foreign"), Err(JrError::NotDatabase)));
+  ///
+  /// //written by a newer format version
+  /// std::fs::write("doc_newer.db", [b'J', b'R', b'D', b'B', 0, 99, 0, 0, 0, 0]).unwrap();
+  /// assert!(matches!(Database::from("doc_newer"), Err(JrError::UnsupportedVersion(99))));
+  ///
+  /// //valid file header but a broken root document
+  /// let mut data = vec![b'J', b'R', b'D', b'B', 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+  /// data.extend_from_slice(&[0, 0, 4, 0, 0, 0, 99]);
+  /// std::fs::write("doc_corrupt.db", data).unwrap();
+  /// assert!(matches!(Database::from("doc_corrupt"), Err(JrError::Corrupt{ offset:18 })));
+  ///
+  /// //databases created before the file header existed are still opened
+  /// std::fs::write("doc_legacy.db", [0, 0, 4, 0, 0, 0, 11, 114, 111, 111, 116]).unwrap();
+  /// assert!(Database::from("doc_legacy").is_ok());
+  /// ```
+  pub fn from(s:&str)->Result<Database, JrError>{
+
+    let path = format!("{}.db",s);
+    let wal_path = Wal::path(Path::new(&path));
+    //a temporary file left by a crash during checkpoint is never the database
+    let _ = fs::remove_file(jrdb_file::tmp_path(Path::new(&path)));
+
+    let mut db_data = match fs::read(&path) {
+      Ok(db_data) => db_data,
+      Err(e) if e.kind() == ErrorKind::NotFound => {
+        //a log left by a removed database must not be replayed on a new one
+        jrdb_wal::remove(&wal_path)?;
+        let db_data = jrdb_file::new_database();
+        jrdb_file::write_atomic(Path::new(&path), &db_data)?;
+        db_data
+      },
+      Err(e) => return Err(e.into()),
+    };
 
-      let mut db_file = db_file;
-      let mut db_data = Vec::new();
-      db_file.read_to_end(&mut db_data).unwrap();
+    let header = FileHeader::read(&mut db_data)?;
+    let (wal, frames) = Wal::open(&wal_path, header.checkpoint)?;
+
+    let mut db = Database{
+      data:db_data,
+      file_name:String::from(s),
+      actions:vec![],
+      wal,
+      pending:vec![],
+      replaced:vec![],
+      indexes:vec![],
+      transaction:None
+    };
 
-      Database{
-        _file:db_file,
-        data:db_data,
-        file_name:String::from(s),
-        actions:vec![]
+    for frame in frames.iter() {
+      for splice in frame.iter() {
+        if splice.start > splice.end || splice.end > db.data.len() {
+          return Err(JrError::Corrupt{ offset:splice.start });
+        }
+        db.data.splice(splice.start..splice.end, splice.data.iter().cloned());
       }
+    }
+
+    //the root document must cover the rest of the file
+    let root = db.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    if root.content_type != 0 || root.content_end != db.data.len() {
+      return Err(JrError::Corrupt{ offset:FILE_HEADER_LEN });
+    }
+    db.load_indexes()?;
+
+    //rewrite a file of an older format right away
+    if header.version < FORMAT_VERSION {
+      db.checkpoint()?;
+    }
+
+    Ok(db)
+  }
+
+  /// Write every committed change into the `.db` file and empty the
+  /// write-ahead log. This happens on its own once the log grows past 1 MiB.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::JrCollection;
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_checkpoint.db");
+  /// let mut db:Database = Database::from("doc_checkpoint").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() }).execute().unwrap();
+  ///
+  /// //the insert is only in the write-ahead log, it is replayed on open
+  /// let wal_size = std::fs::metadata("doc_checkpoint.wal").unwrap().len();
+  /// let mut db:Database = Database::from("doc_checkpoint").unwrap();
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 1);
+  ///
+  /// //after a checkpoint the log is empty and the file holds the insert
+  /// db.checkpoint().unwrap();
+  /// assert!(std::fs::metadata("doc_checkpoint.wal").unwrap().len() < wal_size);
+  /// let mut db:Database = Database::from("doc_checkpoint").unwrap();
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 1);
+  ///
+  /// //a frame torn by a crash is ignored
+  /// db.insert("users", jr_doc!{ "name"; String => "Mathew".into() }).execute().unwrap();
+  /// let mut wal = std::fs::read("doc_checkpoint.wal").unwrap();
+  /// wal.truncate(wal.len()-3);
+  /// std::fs::write("doc_checkpoint.wal", wal).unwrap();
+  /// let mut db:Database = Database::from("doc_checkpoint").unwrap();
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 1);
+  /// ```
+  pub fn checkpoint(&mut self)->Result<(), JrError>{
+    let mut header = FileHeader::read(&mut self.data)?;
+    header.version = FORMAT_VERSION;
+    header.checkpoint += 1;
+    self.data.splice(0..FILE_HEADER_LEN, header.get_bytes());
+
+    //an open transaction is not written, only what was committed before it
+    if let Some((committed, _)) = self.transaction.as_mut() {
+      committed.splice(0..FILE_HEADER_LEN, header.get_bytes());
+    }
+    let data = self.transaction.as_ref().map_or(&self.data, |(committed, _)| committed);
 
-    } else {
+    let path = format!("{}.db", &self.file_name);
+    jrdb_file::write_atomic(Path::new(&path), data)?;
+    self.wal.reset(header.checkpoint)
+  }
+
+  /// Start a transaction. Inserts, updates and deletes executed after this
+  /// are only written to the database by `commit`, `rollback` discards them.
+  /// Calling it again while a transaction is open has no effect.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::JrCollection;
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_transaction.db");
+  /// let mut db:Database = Database::from("doc_transaction").unwrap();
+  ///
+  /// db.transaction();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() }).execute().unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Mathew".into() }).execute().unwrap();
+  ///
+  /// //changes are visible inside the transaction
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 2);
+  ///
+  /// //rollback discards both inserts
+  /// db.rollback();
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 0);
+  ///
+  /// db.transaction();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() }).execute().unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Mathew".into() }).execute().unwrap();
+  ///
+  /// //nothing is written before commit
+  /// let mut other:Database = Database::from("doc_transaction").unwrap();
+  /// let collection: JrCollection = other.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 0);
+  ///
+  /// db.commit().unwrap();
+  /// let mut other:Database = Database::from("doc_transaction").unwrap();
+  /// let collection: JrCollection = other.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 2);
+  /// ```
+  pub fn transaction(&mut self){
+    if self.transaction.is_none() {
+      self.transaction = Some((self.data.clone(), self.indexes.clone()));
+    }
+  }
 
-      let mut db_data = vec![0, 0, 4, 0, 0, 0, 11, 114, 111, 111, 116];
-      let mut db_file = OpenOptions::new()
-      .read(true)
-      .write(true)
-      .create(true).open(format!("{}.db",s)).unwrap();
+  /// Write every change made since `transaction` to the database as a single
+  /// frame of the write-ahead log and close the transaction. Does nothing when
+  /// no transaction is open.
+  pub fn commit(&mut self)->Result<(), JrError>{
+    if self.transaction.is_none() {
+      return Ok(());
+    }
+    self.write_pending()?;
+    self.transaction = None;
+    self.auto_checkpoint()
+  }
+
+  /// Discard every change made since `transaction` and close the transaction.
+  /// Does nothing when no transaction is open.
+  pub fn rollback(&mut self){
+    if let Some((data, indexes)) = self.transaction.take() {
+      self.data = data;
+      self.indexes = indexes;
+      self.pending.clear();
+      self.replaced.clear();
+    }
+  }
+
+  /// Index fields of a collection, each field can be a dot separated path.
+  /// The index is stored in the database and kept up to date by insert,
+  /// update and delete. Conditions comparing the fields with a value by
+  /// `==`, `>`, `>=`, `<` or `<=` use it to read only the documents that can
+  /// match instead of the whole collection. Creating an index that exists
+  /// does nothing, unless it exists as a unique index.
+  ///
+  /// An index of many fields is used by the `and` of an `==` on its first
+  /// fields and at most one range on the next field, like `tenant_id == 1`
+  /// with `created_at >= 100` for `["tenant_id", "created_at"]`. A field
+  /// going through a collection, like `tags.name`, indexes every item.
+  ///
+  /// The stored entries are spread over 256 pages by `_id`, a write reads
+  /// the headers of the pages and the entries of one page only.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection };
+  /// use jrdb::{ and, exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_create_index.db");
+  /// let mut db:Database = Database::from("doc_create_index").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into(), "age"; i64 => 30 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Amy".into(), "age"; i64 => 25 })
+  ///   .execute().unwrap();
+  ///
+  /// db.create_index("users", &["age"]).unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Mathew".into(), "age"; i64 => 41 })
+  ///   .update("users", jr_doc!{ "age"; i64 => 26 })
+  ///   .condition(exp!{"name" ;== "Amy"})
+  ///   .execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users")
+  ///   .condition(exp!{"age" ;> 26})
+  ///   .execute().unwrap();
+  /// assert_eq!(collection.len(), 2);
+  ///
+  /// //the index is stored with the data
+  /// db.delete("users").condition(exp!{"age" ;== 41}).execute().unwrap();
+  /// let mut db:Database = Database::from("doc_create_index").unwrap();
+  /// let collection: JrCollection = db.select("users")
+  ///   .condition(exp!{"age" ;>= 26})
+  ///   .execute().unwrap();
+  /// let name:String = collection.get(1).get_value("name").unwrap();
+  /// assert_eq!(collection.len(), 2);
+  /// assert_eq!(name, "Amy");
+  ///
+  /// //compound and multikey indexes
+  /// let mut tags = JrCollection::new();
+  /// tags.add(jr_doc!{ "name"; String => "rust".into() });
+  /// tags.add(jr_doc!{ "name"; String => "db".into() });
+  /// db.insert("posts", jr_doc!{ "tenant_id"; i64 => 1, "created_at"; i64 => 100 })
+  ///   .insert("posts", jr_doc!{ "tenant_id"; i64 => 1, "created_at"; i64 => 200 })
+  ///   .insert("posts", jr_doc!{ "tenant_id"; i64 => 2, "created_at"; i64 => 300, "tags"; JrCollection => tags })
+  ///   .execute().unwrap();
+  /// db.create_index("posts", &["tenant_id", "created_at"]).unwrap();
+  /// db.create_index("posts", &["tags.name"]).unwrap();
+  ///
+  /// let collection: JrCollection = db.select("posts")
+  ///   .condition(and!{ exp!{"tenant_id" ;== 1}, exp!{"created_at" ;> 100} })
+  ///   .execute().unwrap();
+  /// let created_at:i64 = collection.get(0).get_value("created_at").unwrap();
+  /// assert_eq!(collection.len(), 1);
+  /// assert_eq!(created_at, 200);
+  ///
+  /// let collection: JrCollection = db.select("posts")
+  ///   .condition(exp!{"tags.name" ;== "db"})
+  ///   .execute().unwrap();
+  /// let tenant_id:i64 = collection.get(0).get_value("tenant_id").unwrap();
+  /// assert_eq!(collection.len(), 1);
+  /// assert_eq!(tenant_id, 2);
+  /// ```
+  pub fn create_index(&mut self, from:&str, fields:&[&str])->Result<(), JrError>{
+    let mut actions = [Action{
+      keys:fields.iter().map(|field| field.to_string()).collect(),
+      ..Action::new(ActionType::CreateIndex, from)
+    }];
+    self.execute_actions(&mut actions)?;
+    Ok(())
+  }
 
-      db_file.write(&mut db_data).unwrap();
+  /// Index fields like `create_index` and reject any insert or update that
+  /// would give two documents of the collection the same values, including
+  /// documents of the same `execute`. Documents missing one of the fields
+  /// are not checked.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  /// use jrdb::jrdb_type::JrCollection;
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_unique_index.db");
+  /// let mut db:Database = Database::from("doc_unique_index").unwrap();
+  /// db.create_unique_index("users", &["email"]).unwrap();
+  /// db.insert("users", jr_doc!{ "email"; String => "joel@mail.com".into() })
+  ///   .insert("users", jr_doc!{ "email"; String => "amy@mail.com".into() })
+  ///   .execute().unwrap();
+  ///
+  /// let result = db.insert("users", jr_doc!{ "email"; String => "joel@mail.com".into() }).execute();
+  /// assert!(matches!(result, Err(JrError::DuplicateKey(_))));
+  ///
+  /// let result = db.update("users", jr_doc!{ "email"; String => "joel@mail.com".into() })
+  ///   .condition(exp!{"email" ;== "amy@mail.com"})
+  ///   .execute();
+  /// assert!(matches!(result, Err(JrError::DuplicateKey(_))));
+  ///
+  /// //duplicates inside one execute are rejected too, nothing is written
+  /// let result = db.insert("users", jr_doc!{ "email"; String => "ben@mail.com".into() })
+  ///   .insert("users", jr_doc!{ "email"; String => "ben@mail.com".into() })
+  ///   .execute();
+  /// assert!(matches!(result, Err(JrError::DuplicateKey(_))));
+  ///
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 2);
+  ///
+  /// //an index can not be made unique over existing duplicates
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() })
+  ///   .insert("users", jr_doc!{ "name"; String => "Joel".into() })
+  ///   .execute().unwrap();
+  /// assert!(matches!(db.create_unique_index("users", &["name"]), Err(JrError::DuplicateKey(_))));
+  /// ```
+  pub fn create_unique_index(&mut self, from:&str, fields:&[&str])->Result<(), JrError>{
+    let mut actions = [Action{
+      keys:fields.iter().map(|field| field.to_string()).collect(),
+      ..Action::new(ActionType::CreateUniqueIndex, from)
+    }];
+    self.execute_actions(&mut actions)?;
+    Ok(())
+  }
 
-      Database{
-        _file:db_file,
-        data:db_data,
-        file_name:String::from(s),
-        actions:vec![]
+  /// Names of the collections in the database, in the order they were
+  /// created.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_collections.db");
+  /// let mut db:Database = Database::from("doc_collections").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into(), "age"; i64 => 30 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Amy".into(), "age"; i64 => 25 })
+  ///   .insert("logs", jr_doc!{ "message"; String => "start".into() })
+  ///   .execute().unwrap();
+  /// db.create_index("users", &["age"]).unwrap();
+  /// assert_eq!(db.list_collections().unwrap(), vec!["users", "logs"]);
+  ///
+  /// let stats = db.collection_stats("users").unwrap();
+  /// assert_eq!(stats.documents, 2);
+  /// assert_eq!(stats.avg_document_size, stats.size / 2);
+  /// assert_eq!(stats.indexes, vec!["users.age"]);
+  ///
+  /// //documents and indexes move with the collection
+  /// db.rename_collection("users", "members").unwrap();
+  /// let collection: JrCollection = db.select("members")
+  ///   .condition(exp!{"age" ;< 30})
+  ///   .execute().unwrap();
+  /// let name:String = collection.get(0).get_value("name").unwrap();
+  /// assert_eq!(name, "Amy");
+  /// assert_eq!(db.collection_stats("members").unwrap().indexes, vec!["members.age"]);
+  ///
+  /// db.drop_collection("logs").unwrap();
+  /// assert_eq!(db.list_collections().unwrap(), vec!["members"]);
+  /// assert!(matches!(db.collection_stats("logs"), Err(JrError::NotFound(_))));
+  /// ```
+  pub fn list_collections(&mut self)->Result<Vec<String>, JrError>{
+    let root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let mut names = vec![];
+    let mut pos = root.content_start;
+    while pos < root.content_end {
+      let header = self.get_child_header_by_pos(&root, pos)?;
+      //root documents like $indexes are not collections
+      if header.content_type == 1 {
+        names.push(header.key.clone());
       }
+      pos = header.content_end;
+    }
+    Ok(names)
+  }
+
+  /// Remove a collection with its documents and indexes, see
+  /// `list_collections` for an example.
+  pub fn drop_collection(&mut self, name:&str)->Result<(), JrError>{
+    let mut actions = [Action::new(ActionType::DropCollection, name)];
+    self.execute_actions(&mut actions)?;
+    Ok(())
+  }
+
+  /// Give the collection `name` the name `new_name`, its documents keep their
+  /// `_id` and its indexes move with it. See `list_collections` for an
+  /// example.
+  pub fn rename_collection(&mut self, name:&str, new_name:&str)->Result<(), JrError>{
+    let mut actions = [Action{
+      keys:vec![new_name.to_string()],
+      ..Action::new(ActionType::RenameCollection, name)
+    }];
+    self.execute_actions(&mut actions)?;
+    Ok(())
+  }
 
+  /// Number and size of the documents of a collection and its indexes,
+  /// counted from the headers without reading the documents. See
+  /// `list_collections` for an example.
+  pub fn collection_stats(&mut self, name:&str)->Result<CollectionStats, JrError>{
+    let root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let collection = self.get_by_key_from_doc(&root, name, 1)?;
+    if !collection.found {
+      return Err(JrError::NotFound(name.into()));
     }
 
+    let documents = self.count_documents(name)?;
+    let size = collection.content_end - collection.content_start;
+    Ok(CollectionStats{
+      documents,
+      size,
+      avg_document_size:size.checked_div(documents).unwrap_or(0),
+      indexes:self.indexes.iter()
+        .filter(|index| index.collection == name)
+        .map(|index| index.target())
+        .collect(),
+    })
   }
 
   /// Execute the query
   /// 
+  /// Insert, update and delete are durable once this returns, they are
+  /// appended to a write-ahead log next to the database file which is replayed
+  /// when the database is opened again. The queued actions apply together or
+  /// not at all, when one fails the changes of the others are undone.
+  /// 
   /// # Examples
   /// ```
   /// use jrdb::Database;
-  /// use jrdb::jrdb_type::JrDocument;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrDocument };
   /// 
   /// fn main() {
-  ///   let db:Database = Database::from("main");
+  ///   let mut db:Database = Database::from("doc_execute").unwrap();
   /// 
   ///   //create JrDocument
   ///   let mut doc = JrDocument::new();
@@ -127,26 +684,159 @@ impl Database{
   ///   doc.add_value("age", 30);
   /// 
   ///   //create collection "users" if not exist
-  ///   db.insert("users", doc).execute();
+  ///   db.insert("users", doc).execute().unwrap();
   /// }
   /// ```
-  pub fn execute(&mut self)->JrCollection{
-    let mut data:JrCollection = JrCollection::new();
-    let mut actions = mem::replace(&mut self.actions, Vec::new());
+  ///
+  /// A failing action undoes the ones queued before it.
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::JrCollection;
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_execute_batch.db");
+  /// let mut db:Database = Database::from("doc_execute_batch").unwrap();
+  ///
+  /// //"name" is a string so "name.first" can not be set
+  /// let result = db.insert("users", jr_doc!{ "name"; String => "Joel".into() })
+  ///   .update("users", jr_doc!{ "name.first"; String => "Joel".into() })
+  ///   .execute();
+  /// assert!(result.is_err());
+  ///
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 0);
+  /// ```
+  pub fn execute(&mut self)->Result<JrCollection, JrError>{
+    let mut actions = mem::take(&mut self.actions);
+    let results = self.execute_actions(&mut actions)?;
+
+    let mut data = JrCollection::new();
+    for (elem, result) in actions.iter().zip(results) {
+      if let ActionType::Select | ActionType::Aggregate = elem.action_type {
+        data = result.documents;
+      }
+    }
+    Ok(data)
+  }
+
+  /// Execute the queued actions like `execute` and return what each of them
+  /// did, in the order they were queued.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrDocument };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_execute_results.db");
+  /// let mut db:Database = Database::from("doc_execute_results").unwrap();
+  /// let results = db.insert("users", jr_doc!{ "name"; String => "Joel".into(), "age"; i64 => 30 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Amy".into(), "age"; i64 => 25 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Mathew".into(), "age"; i64 => 30 })
+  ///   .execute_results().unwrap();
+  /// let ids:Vec<&str> = results.iter().map(|result| result.inserted_ids[0].as_str()).collect();
+  /// assert_eq!(ids, vec!["1", "2", "3"]);
+  ///
+  /// let results = db.update("users", jr_doc!{ "age"; i64 => 30 })
+  ///   .condition(exp!{"age" ;>= 25})
+  ///   .delete("users")
+  ///   .condition(exp!{"name" ;== "Mathew"})
+  ///   .select("users")
+  ///   .execute_results().unwrap();
+  /// assert_eq!((results[0].matched, results[0].modified), (3, 1));
+  /// assert_eq!(results[1].deleted, 1);
+  /// assert_eq!(results[2].documents.len(), 2);
+  ///
+  /// //the documents before and after the update
+  /// let results = db.update("users", jr_doc!{ "age"; i64 => 31 })
+  ///   .condition(exp!{"name" ;== "Joel"})
+  ///   .returning()
+  ///   .execute_results().unwrap();
+  /// let before:i64 = results[0].before.get(0).get_value("age").unwrap();
+  /// let after:i64 = results[0].after.get(0).get_value("age").unwrap();
+  /// assert_eq!((before, after), (30, 31));
+  /// ```
+  pub fn execute_results(&mut self)->Result<Vec<ExecuteResult>, JrError>{
+    let mut actions = mem::take(&mut self.actions);
+    self.execute_actions(&mut actions)
+  }
+
+  /// Keep the documents before and after the last queued action in its
+  /// result from `execute_results`: the inserted document, the documents
+  /// before and after an update, upsert or replace, and the deleted ones.
+  pub fn returning(&mut self)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].returning = true;
+    self
+  }
+
+  /// Run `actions` together, when one fails the changes of the others are
+  /// undone.
+  fn execute_actions(&mut self, actions:&mut [Action])->Result<Vec<ExecuteResult>, JrError>{
+    //changes to go back to when one of the actions fails
+    let pending_len = self.pending.len();
+
+    let mut result = self.run_actions(actions);
+    if result.is_ok() && self.transaction.is_none() {
+      result = self.write_pending().and(result);
+    }
+
+    if result.is_err() {
+      if self.pending.len() > pending_len {
+        self.undo_pending(pending_len);
+        //the indexes are read back from the restored data, the error of the
+        //action is kept when that fails too
+        if let Err(rollback) = self.load_indexes() {
+          return match result {
+            Err(error) => Err(JrError::RollbackFailed{ error:Box::new(error), rollback:Box::new(rollback) }),
+            ok => ok,
+          };
+        }
+      }
+      return result;
+    }
+
+    if self.transaction.is_none() {
+      self.auto_checkpoint()?;
+    }
+    result
+  }
+
+  fn run_actions(&mut self, actions:&mut [Action])->Result<Vec<ExecuteResult>, JrError>{
+    let mut results = vec![];
     for elem in actions.iter_mut() {
       let action_type = &elem.action_type;
-      if let ActionType::Insert = action_type{
-        self.insert_action(elem);
+      let result = if let ActionType::Insert = action_type{
+        self.insert_action(elem)?
       }else if let ActionType::Select = action_type{
-        data = self.select_action(elem);
+        ExecuteResult{ documents:self.select_action(elem)?, ..ExecuteResult::default() }
       }else if let ActionType::Update = action_type{
-        self.update_action(elem);
+        self.update_action(elem)?
+      }else if let ActionType::UpdateForce = action_type{
+        self.upsert_action(elem)?
+      }else if let ActionType::Replace = action_type{
+        self.replace_action(elem)?
       }else if let ActionType::Delete = action_type{
-        self.delete_action(elem);
-      }
+        self.delete_action(elem)?
+      }else if let ActionType::Aggregate = action_type{
+        ExecuteResult{ documents:self.aggregate_action(elem)?, ..ExecuteResult::default() }
+      }else if let ActionType::CreateIndex = action_type{
+        self.create_index_action(elem, false)?;
+        ExecuteResult::default()
+      }else if let ActionType::CreateUniqueIndex = action_type{
+        self.create_index_action(elem, true)?;
+        ExecuteResult::default()
+      }else if let ActionType::DropCollection = action_type{
+        self.drop_collection_action(elem)?;
+        ExecuteResult::default()
+      }else{
+        self.rename_collection_action(elem)?;
+        ExecuteResult::default()
+      };
+      results.push(result);
     }
 
-    data
+    Ok(results)
   }
 
   /// Insert data into collection by provide a JrDocument and collection name.
@@ -155,10 +845,10 @@ impl Database{
   /// # Examples
   /// ```
   /// use jrdb::Database;
-  /// use jrdb::jrdb_type::JrDocument;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrDocument };
   /// 
   /// fn main() {
-  ///   let db:Database = Database::from("main");
+  ///   let mut db:Database = Database::from("doc_insert").unwrap();
   /// 
   ///   //create JrDocument
   ///   let mut doc = JrDocument::new();
@@ -167,245 +857,1655 @@ impl Database{
   ///   doc.add_value("age", 30);
   /// 
   ///   //create collection "users" if not exist
-  ///   db.insert("users", doc).execute();
+  ///   db.insert("users", doc).execute().unwrap();
   /// }
   /// ```
+  ///
+  /// Values can be nested JrDocument and JrCollection, they are read back by
+  /// select and can be replaced by update.
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_nested.db");
+  /// let mut db:Database = Database::from("doc_nested").unwrap();
+  ///
+  /// let mut tags = JrCollection::new();
+  /// tags.add(jr_doc!{ "name"; String => "admin".into() });
+  /// tags.add(jr_doc!{ "name"; String => "staff".into() });
+  ///
+  /// let doc = jr_doc!{
+  ///   "name"; String => "Joel".into(),
+  ///   "address"; JrDocument => jr_doc!{ "city"; String => "Penang".into() },
+  ///   "tags"; JrCollection => tags,
+  /// };
+  /// db.insert("users", doc).execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// let address:JrDocument = collection.get(0).get_value("address").unwrap();
+  /// let city:String = address.get_value("city").unwrap();
+  /// assert_eq!(city, "Penang");
+  /// let tags:JrCollection = collection.get(0).get_value("tags").unwrap();
+  /// let tag:String = tags.get(1).get_value("name").unwrap();
+  /// assert_eq!(tags.len(), 2);
+  /// assert_eq!(tag, "staff");
+  ///
+  /// db.update("users", jr_doc!{
+  ///   "address"; JrDocument => jr_doc!{ "city"; String => "Ipoh".into() }
+  /// }).execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// let address:JrDocument = collection.get(0).get_value("address").unwrap();
+  /// let city:String = address.get_value("city").unwrap();
+  /// let tags:JrCollection = collection.get(0).get_value("tags").unwrap();
+  /// assert_eq!(city, "Ipoh");
+  /// assert_eq!(tags.len(), 2);
+  /// ```
+  ///
+  /// Each document gets an `_id` from a counter kept by its collection, ids
+  /// are not reused after delete. An `_id` can also be given, it must be a
+  /// String or i64 not used by another document of the collection. The
+  /// counter is stored as a u32, so a numeric `_id` must be below
+  /// `u32::MAX`, generated ids are checked against the collection as well.
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_insert_id.db");
+  /// let mut db:Database = Database::from("doc_insert_id").unwrap();
+  ///
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() })
+  ///   .insert("users", jr_doc!{ "name"; String => "Mathew".into() })
+  ///   .execute().unwrap();
+  /// db.delete("users").condition(exp!{"name" ;== "Mathew"}).execute().unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Jason".into() }).execute().unwrap();
+  ///
+  /// //"2" is not given again
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// let id:String = collection.get(1).get_value("_id").unwrap();
+  /// assert_eq!(id, "3");
+  ///
+  /// db.insert("users", jr_doc!{
+  ///   "_id"; String => "admin".into(),
+  ///   "name"; String => "Root".into(),
+  /// }).insert("users", jr_doc!{
+  ///   "_id"; i64 => 10,
+  ///   "name"; String => "Amy".into(),
+  /// }).insert("users", jr_doc!{
+  ///   "name"; String => "Ben".into(),
+  /// }).execute().unwrap();
+  ///
+  /// //generated ids continue after the largest numeric id given
+  /// let collection: JrCollection = db.select("users")
+  ///   .condition(exp!{"name" ;== "Ben"})
+  ///   .execute().unwrap();
+  /// let id:String = collection.get(0).get_value("_id").unwrap();
+  /// assert_eq!(id, "11");
+  ///
+  /// let result = db.insert("users", jr_doc!{ "_id"; String => "admin".into() }).execute();
+  /// assert!(matches!(result, Err(JrError::DuplicateId(_))));
+  ///
+  /// let result = db.insert("users", jr_doc!{ "_id"; i64 => u32::MAX as i64 }).execute();
+  /// assert!(matches!(result, Err(JrError::InvalidQuery(_))));
+  ///
+  /// db.insert("users", jr_doc!{ "_id"; i64 => u32::MAX as i64 - 1 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Cid".into() })
+  ///   .execute().unwrap();
+  /// let result = db.insert("users", jr_doc!{ "name"; String => "Dan".into() }).execute();
+  /// assert!(matches!(result, Err(JrError::InvalidQuery(_))));
+  /// assert_eq!(db.collection_stats("users").unwrap().documents, 7);
+  /// ```
   pub fn insert(&mut self, from:&str, doc:JrDocument)->&mut Self{
     self.actions.push(Action{
-      action_type:ActionType::Insert,
-      condition:cond_true!(),
-      from:from.into(),
-      keys:vec![],
-      data:vec![doc]
+      data:vec![doc],
+      ..Action::new(ActionType::Insert, from)
     });
     self
   }
 
-  /// Select data from database
-  /// 
-  /// # Examples
-  /// ```
-  /// use jrdb::Database;
-  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
-  /// 
-  /// fn main(){
-  ///   let db:Database = Database::from("main");
-  /// 
-  ///   //create JrDocument
-  ///   let mut doc = JrDocument::new();
-  ///   doc.add_value("name", String::from("Joel"));
-  ///   doc.add_value("pass", String::from("ILoveErd"));
-  ///   doc.add_value("age", 30);
-  /// 
-  ///   //create collection "users" if not exist
-  ///   db.insert("users", doc).execute();
-  ///   
-  ///   //select all data from colection "users"
-  ///   let collection: JrCollection = db.select("users").execute();
-  ///   collection.print(0);
-  /// }
-  /// ```
-  pub fn select(&mut self, from:&str)->&mut Self{
-    self.actions.push(Action{
-      action_type:ActionType::Select,
-      condition:cond_true!(),
-      from:from.into(),
-      keys:vec![],
-      data:vec![]
-    });
+  /// Insert `docs` into the collection `from` as one action, the documents
+  /// are appended together and the collection headers are written once, so
+  /// it is much faster than one `insert` per document. Ids are given like
+  /// `insert`, when one document fails none of them are inserted.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_insert_many.db");
+  /// let mut db:Database = Database::from("doc_insert_many").unwrap();
+  /// let docs = (1..=3).map(|age| jr_doc!{ "age"; i64 => age }).collect();
+  /// let results = db.insert_many("users", docs).execute_results().unwrap();
+  /// assert_eq!(results[0].inserted_ids, vec!["1", "2", "3"]);
+  ///
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// let age:i64 = collection.get(2).get_value("age").unwrap();
+  /// assert_eq!(collection.len(), 3);
+  /// assert_eq!(age, 3);
+  ///
+  /// //"4" is given to the first document, so the second one repeats it
+  /// let result = db.insert_many("users", vec![
+  ///   jr_doc!{ "age"; i64 => 4 },
+  ///   jr_doc!{ "_id"; String => "4".into(), "age"; i64 => 5 },
+  /// ]).execute();
+  /// assert!(matches!(result, Err(JrError::DuplicateId(_))));
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 3);
+  /// ```
+  pub fn insert_many(&mut self, from:&str, docs:Vec<JrDocument>)->&mut Self{
+    self.actions.push(Action{
+      data:docs,
+      ..Action::new(ActionType::Insert, from)
+    });
+    self
+  }
+
+  /// Select data from database
+  /// 
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// 
+  /// fn main(){
+  ///   let mut db:Database = Database::from("doc_select").unwrap();
+  /// 
+  ///   //create JrDocument
+  ///   let mut doc = JrDocument::new();
+  ///   doc.add_value("name", String::from("Joel"));
+  ///   doc.add_value("pass", String::from("ILoveErd"));
+  ///   doc.add_value("age", 30);
+  /// 
+  ///   //create collection "users" if not exist
+  ///   db.insert("users", doc).execute().unwrap();
+  ///   
+  ///   //select all data from colection "users"
+  ///   let collection: JrCollection = db.select("users").execute().unwrap();
+  ///   collection.print(0);
+  /// }
+  /// ```
+  pub fn select(&mut self, from:&str)->&mut Self{
+    self.actions.push(Action::new(ActionType::Select, from));
+
+    self
+  }
+
+  /// Update data from database
+  /// 
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// 
+  /// fn main(){
+  ///   let mut db:Database = Database::from("doc_update").unwrap();
+  /// 
+  ///   //create JrDocument
+  ///   let mut doc = JrDocument::new();
+  ///   doc.add_value("name", String::from("Joel"));
+  ///   doc.add_value("pass", String::from("ILoveErd"));
+  ///   doc.add_value("age", 30);
+  /// 
+  ///   //create collection "users" if not exist
+  ///   db.insert("users", doc).execute().unwrap();
+  ///   
+  ///   //select all data from collection "users"
+  ///   let collection: JrCollection = db.select("users").execute().unwrap();
+  ///   collection.print(0);
+  /// 
+  ///   //create JrDocument for update
+  ///   let mut updated_doc = JrDocument::new();
+  ///   updated_doc.add_value("name", String::from("Mathew"));
+  /// 
+  ///   //update all data from collection "users"
+  ///   db.update("users", updated_doc).execute().unwrap();
+  /// 
+  ///   let collection: JrCollection = db.select("users").execute().unwrap();
+  ///   collection.print(0);
+  /// }
+  /// ```
+  ///
+  /// Keys can be a dot separated path to set a nested field, missing
+  /// documents along the path are created. The same paths work in conditions
+  /// and after the collection name to select only part of each document.
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_update_path.db");
+  /// let mut db:Database = Database::from("doc_update_path").unwrap();
+  ///
+  /// db.insert("users", jr_doc!{
+  ///   "name"; String => "Joel".into(),
+  ///   "address"; JrDocument => jr_doc!{
+  ///     "city"; String => "Penang".into(),
+  ///     "zip"; i64 => 10250,
+  ///   },
+  /// }).insert("users", jr_doc!{
+  ///   "name"; String => "Mathew".into(),
+  /// }).execute().unwrap();
+  ///
+  /// //only "address.city" is rewritten, "address.zip" is kept
+  /// db.update("users", jr_doc!{ "address.city"; String => "Ipoh".into() })
+  ///   .condition(exp!{"address.city" ;== "Penang"})
+  ///   .execute().unwrap();
+  ///
+  /// //"address" is created for Mathew
+  /// db.update("users.address", jr_doc!{ "city"; String => "Klang".into() })
+  ///   .condition(exp!{"name" ;== "Mathew"})
+  ///   .execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users")
+  ///   .condition(exp!{"address.zip" ;== 10250})
+  ///   .execute().unwrap();
+  /// let address:JrDocument = collection.get(0).get_value("address").unwrap();
+  /// let city:String = address.get_value("city").unwrap();
+  /// assert_eq!(collection.len(), 1);
+  /// assert_eq!(city, "Ipoh");
+  ///
+  /// //select only "address.city" of each document
+  /// let collection: JrCollection = db.select("users.address.city").execute().unwrap();
+  /// let address:JrDocument = collection.get(1).get_value("address").unwrap();
+  /// let city:String = address.get_value("city").unwrap();
+  /// assert_eq!(city, "Klang");
+  /// assert!(collection.get(1).get_any("name").is_none());
+  /// assert!(address.get_any("zip").is_none());
+  ///
+  /// //a path can not go through a value that is not a document
+  /// let result = db.update("users", jr_doc!{ "name.first"; String => "Joel".into() })
+  ///   .execute();
+  /// assert!(matches!(result, Err(JrError::TypeMismatch(_))));
+  ///
+  /// //the _id of a document can not be changed
+  /// let result = db.update("users", jr_doc!{ "_id"; String => "joel".into() })
+  ///   .condition(exp!{"name" ;== "Joel"})
+  ///   .execute();
+  /// assert!(matches!(result, Err(JrError::InvalidQuery(_))));
+  /// let collection: JrCollection = db.select("users")
+  ///   .condition(exp!{"_id" ;== "joel"})
+  ///   .execute().unwrap();
+  /// assert_eq!(collection.len(), 0);
+  /// ```
+  pub fn update(&mut self, from:&str, doc:JrDocument)->&mut Self{
+    self.actions.push(Action{
+      data:vec![doc],
+      ..Action::new(ActionType::Update, from)
+    });
+    self
+  }
+
+  /// Apply an operator to each document of the last queued update, after
+  /// the keys set by the update document. Operators run in the order they
+  /// are added, each one sees the changes of the ones before it.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrAny, JrCollection, JrDocument, JrI64, UpdateOp };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_operator.db");
+  /// let mut db:Database = Database::from("doc_operator").unwrap();
+  /// db.insert("users", jr_doc!{
+  ///   "name"; String => "Joel".into(),
+  ///   "visits"; i64 => 1,
+  ///   "low"; i64 => 50,
+  ///   "tmp"; String => "x".into(),
+  /// }).execute().unwrap();
+  ///
+  /// db.update("users", jr_doc!{ "seen"; i64 => 1 })
+  ///   .operator(UpdateOp::Inc("visits".into(), 2))
+  ///   .operator(UpdateOp::Unset("tmp".into()))
+  ///   .operator(UpdateOp::Rename("name".into(), "profile.name".into()))
+  ///   .operator(UpdateOp::Min("low".into(), JrAny::JrI64(JrI64::new(20))))
+  ///   .operator(UpdateOp::Max("high".into(), JrAny::JrI64(JrI64::new(70))))
+  ///   .operator(UpdateOp::Push("tags".into(), jr_doc!{ "name"; String => "rust".into() }))
+  ///   .operator(UpdateOp::Push("tags".into(), jr_doc!{ "name"; String => "db".into() }))
+  ///   .operator(UpdateOp::Pull("tags".into(), exp!{"name" ;== "rust"}))
+  ///   .execute().unwrap();
+  ///
+  /// let doc = db.get_by_id("users", 1).unwrap().unwrap();
+  /// let visits:i64 = doc.get_value("visits").unwrap();
+  /// let profile:JrDocument = doc.get_value("profile").unwrap();
+  /// let name:String = profile.get_value("name").unwrap();
+  /// let low:i64 = doc.get_value("low").unwrap();
+  /// let high:i64 = doc.get_value("high").unwrap();
+  /// let tags:JrCollection = doc.get_value("tags").unwrap();
+  /// let tag:String = tags.get(0).get_value("name").unwrap();
+  /// assert_eq!((visits, low, high), (3, 20, 70));
+  /// assert_eq!(name, "Joel");
+  /// assert!(doc.get_any("tmp").is_none());
+  /// assert!(doc.get_any("name").is_none());
+  /// assert_eq!(tags.len(), 1);
+  /// assert_eq!(tag, "db");
+  ///
+  /// //nothing is changed when an operator fails
+  /// let result = db.update("users", JrDocument::new())
+  ///   .operator(UpdateOp::Inc("visits".into(), 1))
+  ///   .operator(UpdateOp::Inc("profile.name".into(), 1))
+  ///   .execute();
+  /// assert!(matches!(result, Err(JrError::TypeMismatch(_))));
+  /// let doc = db.get_by_id("users", 1).unwrap().unwrap();
+  /// let visits:i64 = doc.get_value("visits").unwrap();
+  /// assert_eq!(visits, 3);
+  ///
+  /// //keys of a document value must fit in a header
+  /// let mut value = JrDocument::new();
+  /// value.add_value(&"k".repeat(300), 1);
+  /// let result = db.update("users", JrDocument::new())
+  ///   .operator(UpdateOp::Max("high".into(), JrAny::JrDocument(value)))
+  ///   .execute();
+  /// assert!(matches!(result, Err(JrError::KeyTooLong(_))));
+  /// ```
+  pub fn operator(&mut self, op:UpdateOp)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].operators.push(op);
+    self
+  }
+
+  /// Update like `update`, or insert `doc` when no document matches the
+  /// condition. The operators of the update are applied to the inserted
+  /// document too. The search and the insert are a single action, nothing
+  /// else runs on the database in between.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, UpdateOp };
+  /// use jrdb::{ and, exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_upsert.db");
+  /// let mut db:Database = Database::from("doc_upsert").unwrap();
+  /// for _ in 0..2 {
+  ///   db.upsert("pages", jr_doc!{ "path"; String => "/home".into() })
+  ///     .condition(exp!{"path" ;== "/home"})
+  ///     .operator(UpdateOp::Inc("views".into(), 1))
+  ///     .execute().unwrap();
+  /// }
+  ///
+  /// let collection: JrCollection = db.select("pages").execute().unwrap();
+  /// let views:i64 = collection.get(0).get_value("views").unwrap();
+  /// assert_eq!(collection.len(), 1);
+  /// assert_eq!(views, 2);
+  ///
+  /// //the fields compared with == are set on the inserted document
+  /// db.upsert("pages", jr_doc!{ "title"; String => "About".into() })
+  ///   .condition(and!{ exp!{"path" ;== "/about"}, exp!{"lang" ;== "en"} })
+  ///   .merge_condition()
+  ///   .execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("pages")
+  ///   .condition(exp!{"path" ;== "/about"})
+  ///   .execute().unwrap();
+  /// let lang:String = collection.get(0).get_value("lang").unwrap();
+  /// let title:String = collection.get(0).get_value("title").unwrap();
+  /// assert_eq!((lang.as_str(), title.as_str()), ("en", "About"));
+  /// ```
+  pub fn upsert(&mut self, from:&str, doc:JrDocument)->&mut Self{
+    self.actions.push(Action{
+      data:vec![doc],
+      ..Action::new(ActionType::UpdateForce, from)
+    });
+    self
+  }
+
+  /// Replace the whole body of each document matching the condition with
+  /// `doc`, keys missing from `doc` are removed and the `_id` is kept. An
+  /// `_id` in `doc` only replaces the document with that `_id`.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrDocument };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_replace.db");
+  /// let mut db:Database = Database::from("doc_replace").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into(), "pass"; String => "ILoveErd".into() })
+  ///   .insert("users", jr_doc!{ "name"; String => "Amy".into(), "pass"; String => "1234".into() })
+  ///   .execute().unwrap();
+  ///
+  /// db.replace("users", jr_doc!{ "name"; String => "Joel Lee".into(), "address.city"; String => "Penang".into() })
+  ///   .condition(exp!{"name" ;== "Joel"})
+  ///   .execute().unwrap();
+  ///
+  /// let doc = db.get_by_id("users", 1).unwrap().unwrap();
+  /// let name:String = doc.get_value("name").unwrap();
+  /// let address:JrDocument = doc.get_value("address").unwrap();
+  /// let city:String = address.get_value("city").unwrap();
+  /// assert_eq!((name.as_str(), city.as_str()), ("Joel Lee", "Penang"));
+  /// assert!(doc.get_any("pass").is_none());
+  ///
+  /// //a selected document can be changed and written back
+  /// let mut doc = db.get_by_id("users", 2).unwrap().unwrap();
+  /// doc.add_value("pass", String::from("s3cret"));
+  /// db.replace("users", doc).execute().unwrap();
+  /// let doc = db.get_by_id("users", 2).unwrap().unwrap();
+  /// let pass:String = doc.get_value("pass").unwrap();
+  /// assert_eq!(pass, "s3cret");
+  /// ```
+  pub fn replace(&mut self, from:&str, doc:JrDocument)->&mut Self{
+    self.actions.push(Action{
+      data:vec![doc],
+      ..Action::new(ActionType::Replace, from)
+    });
+    self
+  }
+
+  /// Set the fields the condition of the last queued upsert compares with
+  /// `==` on the document it inserts. Only expressions joined by `and` are
+  /// used, keys of the upsert document take precedence.
+  pub fn merge_condition(&mut self)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].merge_condition = true;
+    self
+  }
+
+  /// Delete data from database
+  /// 
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// 
+  /// fn main(){
+  ///   let mut db:Database = Database::from("doc_delete").unwrap();
+  /// 
+  ///   //create JrDocument
+  ///   let mut doc = JrDocument::new();
+  ///   doc.add_value("name", String::from("Joel"));
+  ///   doc.add_value("pass", String::from("ILoveErd"));
+  ///   doc.add_value("age", 30);
+  /// 
+  ///   //create collection "users" if not exist
+  ///   db.insert("users", doc).execute().unwrap();
+  ///   
+  ///   //select all data from collection "users"
+  ///   let collection: JrCollection = db.select("users").execute().unwrap();
+  ///   collection.print(0);
+  ///
+  ///   //delete all data from collection "users"
+  ///   db.delete("users").execute().unwrap();
+  /// 
+  ///   let collection: JrCollection = db.select("users").execute().unwrap();
+  ///   collection.print(0);
+  /// }
+  /// ```
+  pub fn delete(&mut self, from:&str)->&mut Self{
+    self.actions.push(Action::new(ActionType::Delete, from));
+    self
+  }
+
+  /// Aggregate the documents of a collection. The documents matching
+  /// `condition` are grouped by `group_by`, then every `accumulate` is
+  /// computed for each group. The result has one document per group holding
+  /// the group values and the accumulator results, `sort_by`, `skip` and
+  /// `limit` apply to these documents.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ Accumulator, AddGetValue, JrCollection, SortOrder };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_aggregate.db");
+  /// let mut db:Database = Database::from("doc_aggregate").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into(), "city"; String => "Penang".into(), "age"; i64 => 30 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Amy".into(), "city"; String => "Ipoh".into(), "age"; i64 => 25 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Mathew".into(), "city"; String => "Penang".into(), "age"; i64 => 41 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Ben".into(), "city"; String => "Penang".into(), "age"; i64 => 12 })
+  ///   .execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.aggregate("users")
+  ///   .condition(exp!{"age" ;>= 18})
+  ///   .group_by(&["city"])
+  ///   .accumulate("count", Accumulator::Count)
+  ///   .accumulate("total", Accumulator::Sum("age".into()))
+  ///   .accumulate("average", Accumulator::Avg("age".into()))
+  ///   .accumulate("youngest", Accumulator::Min("age".into()))
+  ///   .accumulate("names", Accumulator::Push("name".into()))
+  ///   .sort_by("count", SortOrder::Desc)
+  ///   .execute().unwrap();
+  ///
+  /// assert_eq!(collection.len(), 2);
+  /// let penang = collection.get(0);
+  /// let city:String = penang.get_value("city").unwrap();
+  /// let count:i64 = penang.get_value("count").unwrap();
+  /// let total:i64 = penang.get_value("total").unwrap();
+  /// let average:i64 = penang.get_value("average").unwrap();
+  /// let youngest:i64 = penang.get_value("youngest").unwrap();
+  /// let names:JrCollection = penang.get_value("names").unwrap();
+  /// let name:String = names.get(1).get_value("name").unwrap();
+  /// assert_eq!(city, "Penang");
+  /// assert_eq!((count, total, average, youngest), (2, 71, 35, 30));
+  /// assert_eq!(name, "Mathew");
+  ///
+  /// //without group_by the whole collection is one group
+  /// let collection: JrCollection = db.aggregate("users")
+  ///   .accumulate("oldest", Accumulator::Max("age".into()))
+  ///   .execute().unwrap();
+  /// let oldest:i64 = collection.get(0).get_value("oldest").unwrap();
+  /// assert_eq!(oldest, 41);
+  /// ```
+  pub fn aggregate(&mut self, from:&str)->&mut Self{
+    self.actions.push(Action::new(ActionType::Aggregate, from));
+    self
+  }
+
+  /// Group the documents of an aggregate by the values of these keys, keys
+  /// can be dot separated paths.
+  pub fn group_by(&mut self, keys:&[&str])->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].group = keys.iter().map(|key| key.to_string()).collect();
+    self
+  }
+
+  /// Compute `accumulator` for each group of an aggregate and store it under
+  /// `name` in the result document.
+  pub fn accumulate(&mut self, name:&str, accumulator:Accumulator)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].accumulators.push((name.into(), accumulator));
+    self
+  }
+
+  /// Get the document with `_id` from a collection, `None` when there is no
+  /// such document. Runs right away instead of being queued for `execute`.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::AddGetValue;
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_get_by_id.db");
+  /// let mut db:Database = Database::from("doc_get_by_id").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() })
+  ///   .insert("users", jr_doc!{ "_id"; String => "admin".into(), "name"; String => "Root".into() })
+  ///   .execute().unwrap();
+  ///
+  /// let doc = db.get_by_id("users", 1).unwrap().unwrap();
+  /// let name:String = doc.get_value("name").unwrap();
+  /// let id:String = doc.get_value("_id").unwrap();
+  /// assert_eq!(name, "Joel");
+  /// assert_eq!(id, "1");
+  ///
+  /// let doc = db.get_by_id("users", "admin").unwrap().unwrap();
+  /// let name:String = doc.get_value("name").unwrap();
+  /// assert_eq!(name, "Root");
+  ///
+  /// assert!(db.get_by_id("users", 2).unwrap().is_none());
+  /// assert!(db.get_by_id("admins", 1).unwrap().is_none());
+  /// ```
+  pub fn get_by_id<I:ToString>(&mut self, from:&str, id:I)->Result<Option<JrDocument>, JrError>{
+    let action = Action{
+      id:Some(id.to_string()),
+      ..Action::new(ActionType::Select, from)
+    };
+    Cursor::new(self, action).next().transpose()
+  }
+
+  /// Update the document with `_id` from a collection, it works like `update`
+  /// without looping the whole collection.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::AddGetValue;
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_update_by_id.db");
+  /// let mut db:Database = Database::from("doc_update_by_id").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() })
+  ///   .insert("users", jr_doc!{ "name"; String => "Mathew".into() })
+  ///   .execute().unwrap();
+  ///
+  /// db.update_by_id("users", 2, jr_doc!{ "name"; String => "Jason".into() }).execute().unwrap();
+  ///
+  /// let name:String = db.get_by_id("users", 1).unwrap().unwrap().get_value("name").unwrap();
+  /// assert_eq!(name, "Joel");
+  /// let name:String = db.get_by_id("users", 2).unwrap().unwrap().get_value("name").unwrap();
+  /// assert_eq!(name, "Jason");
+  /// ```
+  pub fn update_by_id<I:ToString>(&mut self, from:&str, id:I, doc:JrDocument)->&mut Self{
+    self.actions.push(Action{
+      id:Some(id.to_string()),
+      data:vec![doc],
+      ..Action::new(ActionType::Update, from)
+    });
+    self
+  }
+
+  /// Delete the document with `_id` from a collection, it works like `delete`
+  /// without looping the whole collection.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::JrCollection;
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_delete_by_id.db");
+  /// let mut db:Database = Database::from("doc_delete_by_id").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() })
+  ///   .insert("users", jr_doc!{ "name"; String => "Mathew".into() })
+  ///   .execute().unwrap();
+  ///
+  /// db.delete_by_id("users", 1).execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// assert_eq!(collection.len(), 1);
+  /// assert!(db.get_by_id("users", 1).unwrap().is_none());
+  /// ```
+  pub fn delete_by_id<I:ToString>(&mut self, from:&str, id:I)->&mut Self{
+    self.actions.push(Action{
+      id:Some(id.to_string()),
+      ..Action::new(ActionType::Delete, from)
+    });
+    self
+  }
+
+  /// Apply condition while select, delete or update the database
+  /// 
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// use jrdb::exp;
+  /// 
+  /// fn main(){
+  ///   let mut db:Database = Database::from("doc_condition").unwrap();
+  /// 
+  ///   //create JrDocument
+  ///   let mut doc = JrDocument::new();
+  ///   doc.add_value("name", String::from("Joel"));
+  ///   doc.add_value("pass", String::from("ILoveErd"));
+  ///   doc.add_value("age", 30);
+  /// 
+  ///   //create collection "users" if not exist
+  ///   db.insert("users", doc).execute().unwrap();
+  ///   
+  ///   //select all data from collection "users"
+  ///   let collection: JrCollection = 
+  ///     db.select("users")
+  ///     .condition(exp! {"name" ;== "Mathew"})
+  ///     .execute().unwrap();
+  ///   
+  ///   //nothing will show since no document with in 'users' with name 'Manthew'
+  ///   collection.print(0);
+  /// 
+  ///   //select all data from collection "users"
+  ///   let collection: JrCollection = 
+  ///     db.select("users")
+  ///     .condition(exp! {"name" ;== "Joel"})
+  ///     .execute().unwrap();
+  ///   
+  ///   //shows document with name 'Joel'
+  ///   collection.print(0);
+  /// }
+  /// ```
+  ///
+  /// Every action sees the `_id` of the documents in its condition.
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_condition_id.db");
+  /// let mut db:Database = Database::from("doc_condition_id").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into() })
+  ///   .insert("users", jr_doc!{ "name"; String => "Mathew".into() })
+  ///   .insert("users", jr_doc!{ "name"; String => "Jason".into() })
+  ///   .execute().unwrap();
+  ///
+  /// db.update("users", jr_doc!{ "admin"; i64 => 1 })
+  ///   .condition(exp!{"_id" ;== 1})
+  ///   .execute().unwrap();
+  /// db.delete("users")
+  ///   .condition(exp!{"_id" ;> 2})
+  ///   .execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users").execute().unwrap();
+  /// let admin:i64 = collection.get(0).get_value("admin").unwrap();
+  /// assert_eq!(collection.len(), 2);
+  /// assert_eq!(admin, 1);
+  /// ```
+  pub fn condition(&mut self, cond:JrCondition)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].condition = cond;
+    self
+  }
+
+  /// Select only these keys of each document, `_id` is always kept. Keys can
+  /// be dot separated paths, only the values needed by the keys and the
+  /// condition are decoded from the database.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_keys.db");
+  /// let mut db:Database = Database::from("doc_keys").unwrap();
+  /// db.insert("users", jr_doc!{
+  ///   "name"; String => "Joel".into(),
+  ///   "pass"; String => "ILoveErd".into(),
+  ///   "age"; i64 => 30,
+  ///   "address"; JrDocument => jr_doc!{
+  ///     "city"; String => "Penang".into(),
+  ///     "zip"; i64 => 10250,
+  ///   },
+  /// }).execute().unwrap();
+  ///
+  /// //the condition can use a key that is not selected
+  /// let collection: JrCollection = db.select("users")
+  ///   .keys(&["name", "address.city"])
+  ///   .condition(exp!{"age" ;== 30})
+  ///   .execute().unwrap();
+  /// let doc = collection.get(0);
+  /// let name:String = doc.get_value("name").unwrap();
+  /// let address:JrDocument = doc.get_value("address").unwrap();
+  /// assert_eq!(name, "Joel");
+  /// assert!(doc.get_any("_id").is_some());
+  /// assert!(doc.get_any("age").is_none());
+  /// assert!(doc.get_any("pass").is_none());
+  /// assert!(address.get_any("city").is_some());
+  /// assert!(address.get_any("zip").is_none());
+  /// ```
+  pub fn keys(&mut self, keys:&[&str])->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].keys = keys.iter().map(|key| key.to_string()).collect();
+    self
+  }
+
+  /// Select every key of each document except these, keys can be dot
+  /// separated paths.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_exclude.db");
+  /// let mut db:Database = Database::from("doc_exclude").unwrap();
+  /// db.insert("users", jr_doc!{
+  ///   "name"; String => "Joel".into(),
+  ///   "pass"; String => "ILoveErd".into(),
+  ///   "address"; JrDocument => jr_doc!{
+  ///     "city"; String => "Penang".into(),
+  ///     "zip"; i64 => 10250,
+  ///   },
+  /// }).execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users")
+  ///   .exclude(&["pass", "address.zip"])
+  ///   .execute().unwrap();
+  /// let doc = collection.get(0);
+  /// let address:JrDocument = doc.get_value("address").unwrap();
+  /// assert!(doc.get_any("name").is_some());
+  /// assert!(doc.get_any("pass").is_none());
+  /// assert!(address.get_any("city").is_some());
+  /// assert!(address.get_any("zip").is_none());
+  /// ```
+  pub fn exclude(&mut self, keys:&[&str])->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].exclude = keys.iter().map(|key| key.to_string()).collect();
+    self
+  }
+
+  /// Order the selected documents by a key, call it again to sort by more
+  /// keys when the previous ones are equal. i64 and String values are
+  /// compared by value, documents missing the key come first.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, SortOrder };
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_sort_by.db");
+  /// let mut db:Database = Database::from("doc_sort_by").unwrap();
+  /// db.insert("users", jr_doc!{ "name"; String => "Joel".into(), "age"; i64 => 30 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Mathew".into(), "age"; i64 => 400 })
+  ///   .insert("users", jr_doc!{ "name"; String => "Amy".into(), "age"; i64 => 30 })
+  ///   .execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users")
+  ///   .sort_by("age", SortOrder::Desc)
+  ///   .sort_by("name", SortOrder::Asc)
+  ///   .execute().unwrap();
+  /// let names:Vec<String> = (0..collection.len())
+  ///   .map(|i| collection.get(i).get_value("name").unwrap())
+  ///   .collect();
+  /// assert_eq!(names, vec!["Mathew", "Amy", "Joel"]);
+  /// ```
+  pub fn sort_by(&mut self, key:&str, order:SortOrder)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].sort.push((key.into(), order));
+    self
+  }
+
+  /// Select at most `limit` documents. Without `sort_by` the collection is
+  /// only read until enough documents are found.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection };
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_limit.db");
+  /// let mut db:Database = Database::from("doc_limit").unwrap();
+  /// for age in 0..10 {
+  ///   db.insert("users", jr_doc!{ "age"; i64 => age });
+  /// }
+  /// db.execute().unwrap();
+  ///
+  /// //third page of 3 documents
+  /// let collection: JrCollection = db.select("users").skip(6).limit(3).execute().unwrap();
+  /// let ages:Vec<i64> = (0..collection.len())
+  ///   .map(|i| collection.get(i).get_value("age").unwrap())
+  ///   .collect();
+  /// assert_eq!(ages, vec![6, 7, 8]);
+  /// ```
+  pub fn limit(&mut self, limit:usize)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].limit = Some(limit);
+    self
+  }
+
+  /// Leave out the first `skip` selected documents, after `sort_by` is
+  /// applied.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, SortOrder };
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_skip.db");
+  /// let mut db:Database = Database::from("doc_skip").unwrap();
+  /// for age in 0..5 {
+  ///   db.insert("users", jr_doc!{ "age"; i64 => age });
+  /// }
+  /// db.execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users")
+  ///   .sort_by("age", SortOrder::Desc)
+  ///   .skip(3)
+  ///   .execute().unwrap();
+  /// let ages:Vec<i64> = (0..collection.len())
+  ///   .map(|i| collection.get(i).get_value("age").unwrap())
+  ///   .collect();
+  /// assert_eq!(ages, vec![1, 0]);
+  /// ```
+  pub fn skip(&mut self, skip:usize)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].skip = skip;
+    self
+  }
+
+  /// Start the select after the document with `_id`, in the order given by
+  /// `sort_by` when there is one. Used with `Cursor::last_id` to read a
+  /// collection page by page across separate calls. The document does not
+  /// have to match the condition, with `sort_by` the select goes on from its
+  /// current sort values so a page can be followed after its last document
+  /// was updated or filtered out.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, SortOrder };
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_after.db");
+  /// let mut db:Database = Database::from("doc_after").unwrap();
+  /// for age in 0..5 {
+  ///   db.insert("users", jr_doc!{ "age"; i64 => age });
+  /// }
+  /// db.execute().unwrap();
+  ///
+  /// let collection: JrCollection = db.select("users").after(3).execute().unwrap();
+  /// let ages:Vec<i64> = (0..collection.len())
+  ///   .map(|i| collection.get(i).get_value("age").unwrap())
+  ///   .collect();
+  /// assert_eq!(ages, vec![3, 4]);
+  ///
+  /// //the last document of the first page no longer matches
+  /// db.update("users", jr_doc!{ "active"; i64 => 1 }).execute().unwrap();
+  /// let mut cursor = db.select("users")
+  ///   .condition(exp!{"active" ;== 1})
+  ///   .sort_by("age", SortOrder::Desc)
+  ///   .limit(2)
+  ///   .iter();
+  /// let page:Vec<i64> = cursor.by_ref().map(|jr_doc| jr_doc.unwrap().get_value("age").unwrap()).collect();
+  /// let last_id = cursor.last_id().unwrap().to_string();
+  /// assert_eq!(page, vec![4, 3]);
+  /// db.update("users", jr_doc!{ "active"; i64 => 0 }).condition(exp!{"_id" ;== last_id.as_str()}).execute().unwrap();
+  /// let collection: JrCollection = db.select("users")
+  ///   .condition(exp!{"active" ;== 1})
+  ///   .sort_by("age", SortOrder::Desc)
+  ///   .after(last_id)
+  ///   .execute().unwrap();
+  /// let ages:Vec<i64> = (0..collection.len())
+  ///   .map(|i| collection.get(i).get_value("age").unwrap())
+  ///   .collect();
+  /// assert_eq!(ages, vec![2, 1, 0]);
+  ///
+  /// //the after document can be outside the documents found by an index
+  /// db.create_index("users", &["age"]).unwrap();
+  /// let collection: JrCollection = db.select("users")
+  ///   .condition(exp!{"age" ;== 4})
+  ///   .after(2)
+  ///   .execute().unwrap();
+  /// assert_eq!(collection.len(), 1);
+  /// let collection: JrCollection = db.select("users")
+  ///   .condition(exp!{"age" ;== 1})
+  ///   .after(4)
+  ///   .execute().unwrap();
+  /// assert_eq!(collection.len(), 0);
+  /// ```
+  pub fn after<I:ToString>(&mut self, id:I)->&mut Self{
+    let i = self.actions.len();
+    self.actions[i-1].after = Some(id.to_string());
+    self
+  }
+
+  /// Read the documents of the last queued select one at a time instead of
+  /// collecting them with `execute`. Other queued actions are kept for the
+  /// next `execute`.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  /// use jrdb::jrdb_type::AddGetValue;
+  /// use jrdb::jr_doc;
+  ///
+  /// # let _ = std::fs::remove_file("doc_iter.db");
+  /// let mut db:Database = Database::from("doc_iter").unwrap();
+  /// for age in 0..10 {
+  ///   db.insert("users", jr_doc!{ "age"; i64 => age });
+  /// }
+  /// db.execute().unwrap();
+  ///
+  /// //stop at the first match, the rest of the collection is not read
+  /// let doc = db.select("users").iter()
+  ///   .find(|doc| doc.as_ref().map_or(true, |doc| doc.get_value("age") == Ok(4i64)))
+  ///   .unwrap().unwrap();
+  /// let id:String = doc.get_value("_id").unwrap();
+  /// assert_eq!(id, "5");
+  ///
+  /// //read a page, then continue after its last document with a new select
+  /// let mut cursor = db.select("users").limit(4).iter();
+  /// assert_eq!(cursor.by_ref().count(), 4);
+  /// let last_id = cursor.last_id().unwrap().to_string();
+  ///
+  /// let ages:Vec<i64> = db.select("users").after(last_id).iter()
+  ///   .map(|doc| doc.unwrap().get_value("age").unwrap())
+  ///   .collect();
+  /// assert_eq!(ages, vec![4, 5, 6, 7, 8, 9]);
+  ///
+  /// //an insert queued last is not a select, it is kept for execute
+  /// db.insert("users", jr_doc!{ "age"; i64 => 10 });
+  /// assert!(matches!(db.iter().next(), Some(Err(JrError::InvalidQuery(_)))));
+  /// db.execute().unwrap();
+  /// assert_eq!(db.select("users").iter().count(), 11);
+  /// ```
+  pub fn iter(&mut self)->Cursor<'_>{
+    let action = match self.actions.last() {
+      Some(Action{ action_type:ActionType::Select, .. }) => self.actions.pop(),
+      _ => None,
+    };
+    //without a select last the queue is kept, the cursor returns an
+    //InvalidQuery error
+    let action = action.unwrap_or_else(|| Action::new(ActionType::Insert, ""));
+    Cursor::new(self, action)
+  }
+
+  /// Run the last queued select and return how its documents were found
+  /// instead of the documents. Other queued actions are kept for the next
+  /// `execute`, when the last queued action is not a select it is kept too
+  /// and `InvalidQuery` is returned.
+  ///
+  /// # Examples
+  /// ```
+  /// use jrdb::Database;
+  /// use jrdb::jrdb_error::JrError;
+  /// use jrdb::jrdb_plan::Scan;
+  /// use jrdb::jrdb_type::SortOrder;
+  /// use jrdb::{ exp, jr_doc };
+  ///
+  /// # let _ = std::fs::remove_file("doc_explain.db");
+  /// let mut db:Database = Database::from("doc_explain").unwrap();
+  /// for age in 0..10 {
+  ///   db.insert("users", jr_doc!{ "age"; i64 => age });
+  /// }
+  /// db.execute().unwrap();
+  ///
+  /// let plan = db.select("users").condition(exp!{"age" ;>= 7}).explain().unwrap();
+  /// assert_eq!(plan.scan, Scan::Full);
+  /// assert_eq!((plan.estimated, plan.examined, plan.returned), (10, 10, 3));
+  ///
+  /// db.create_index("users", &["age"]).unwrap();
+  /// let plan = db.select("users")
+  ///   .condition(exp!{"age" ;>= 7})
+  ///   .sort_by("age", SortOrder::Desc)
+  ///   .limit(2)
+  ///   .explain().unwrap();
+  /// assert_eq!(plan.scan, Scan::Index(vec!["users.age".into()]));
+  /// assert_eq!((plan.estimated, plan.examined, plan.returned), (3, 3, 2));
+  /// assert!(plan.sorted_in_memory);
+  ///
+  /// //an insert queued last is not explained, it still runs on execute
+  /// db.insert("users", jr_doc!{ "age"; i64 => 10 });
+  /// assert!(matches!(db.explain(), Err(JrError::InvalidQuery(_))));
+  /// db.execute().unwrap();
+  /// let plan = db.select("users").explain().unwrap();
+  /// assert_eq!(plan.returned, 11);
+  /// ```
+  pub fn explain(&mut self)->Result<QueryPlan, JrError>{
+    let mut cursor = self.iter();
+    for jr_doc in cursor.by_ref() {
+      jr_doc?;
+    }
+    cursor.plan()
+  }
+
+  /// Make the changes since the last commit durable by appending them to the
+  /// write-ahead log as a single frame.
+  fn write_pending(&mut self)->Result<(), JrError>{
+    if !self.pending.is_empty() {
+      self.wal.append(&self.pending)?;
+      self.pending.clear();
+      self.replaced.clear();
+    }
+    Ok(())
+  }
+
+  /// Undo the pending changes made after the first `len`, latest first.
+  fn undo_pending(&mut self, len:usize){
+    while self.pending.len() > len {
+      if let (Some(splice), Some(replaced)) = (self.pending.pop(), self.replaced.pop()) {
+        self.data.splice(splice.start..splice.start+splice.data.len(), replaced);
+      }
+    }
+  }
+
+  fn auto_checkpoint(&mut self)->Result<(), JrError>{
+    if self.wal.len() > CHECKPOINT_SIZE {
+      self.checkpoint()?;
+    }
+    Ok(())
+  }
+
+  fn insert_action(&mut self, action:&mut Action)->Result<ExecuteResult, JrError>{
+    let mut result = ExecuteResult::default();
+    if action.data.is_empty() {
+      return Ok(result);
+    }
+
+    let mut header_detail = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let ids = self.find_and_insert(&action.from, &mut header_detail, &mut action.data)?;
+    let collection_name = action.from.split('.').next().unwrap_or_default();
+    for (id, doc) in ids.iter().zip(action.data.iter()) {
+      self.index_document(collection_name, id, doc)?;
+      if action.returning {
+        let mut jr_doc = doc.clone();
+        jr_doc.add("_id", JrString::new(id.clone()));
+        result.after.add(jr_doc);
+      }
+    }
+    result.inserted_ids = ids;
+    Ok(result)
+  }
+
+  fn select_action(&mut self, action:&mut Action)->Result<JrCollection, JrError>{
+    let mut jr_collec = JrCollection::new();
+    for jr_doc in Cursor::new(self, action.clone()) {
+      jr_collec.add(jr_doc?);
+    }
+    Ok(jr_collec)
+  }
+
+  fn aggregate_action(&mut self, action:&mut Action)->Result<JrCollection, JrError>{
+    //only the fields used by the groups and accumulators are decoded, "_id"
+    //keeps the projection from decoding everything when there are none
+    let mut keys = vec![String::from("_id")];
+    keys.extend(action.group.iter().cloned());
+    for (_, accumulator) in action.accumulators.iter() {
+      match accumulator {
+        Accumulator::Count => {},
+        Accumulator::Sum(path)
+        | Accumulator::Avg(path)
+        | Accumulator::Min(path)
+        | Accumulator::Max(path)
+        | Accumulator::Push(path) => keys.push(path.clone()),
+      }
+    }
+    let source = Action{
+      action_type:ActionType::Select,
+      keys,
+      exclude:vec![],
+      sort:vec![],
+      limit:None,
+      skip:0,
+      ..action.clone()
+    };
+
+    let mut results = jrdb_aggregate::aggregate(Cursor::new(self, source), &action.group, &action.accumulators)?;
+    if !action.sort.is_empty() {
+      results.sort_by(|a, b| Database::compare_docs(a, b, &action.sort));
+    }
+
+    let mut jr_collec = JrCollection::new();
+    let limit = action.limit.unwrap_or(usize::MAX);
+    for jr_doc in results.into_iter().skip(action.skip).take(limit) {
+      jr_collec.add(jr_doc);
+    }
+    Ok(jr_collec)
+  }
+
+  fn create_index_action(&mut self, action:&mut Action, unique:bool)->Result<(), JrError>{
+    let collection_name = action.from.split('.').next().unwrap_or_default();
+    let fields = action.keys.clone();
+    check_key(collection_name)?;
+    if fields.is_empty() {
+      return Err(JrError::InvalidQuery("an index needs a field".into()));
+    }
+    for field in fields.iter() {
+      for segment in field.split('.') {
+        check_key(segment)?;
+      }
+      if field == "_id" {
+        return Err(JrError::InvalidQuery("_id can not be indexed".into()));
+      }
+    }
+    if let Some(index) = self.indexes.iter().find(|index| index.collection == collection_name && index.fields == fields) {
+      return if index.unique == unique {
+        Ok(())
+      }else{
+        Err(JrError::InvalidQuery(format!("index exists with another unique option: {}", index.target())))
+      };
+    }
+
+    let name = self.indexes.iter()
+      .filter_map(|index| index.name.parse::<u64>().ok())
+      .max().unwrap_or(0) + 1;
+    let mut index = Index::new(&name.to_string(), collection_name, &fields, unique);
+
+    //whole top keys, a path through a collection is not kept by a projection
+    let source = Action{
+      keys:fields.iter().map(|field| field.split('.').next().unwrap_or_default().to_string()).collect(),
+      ..Action::new(ActionType::Select, collection_name)
+    };
+    let mut pages:BTreeMap<String, JrDocument> = BTreeMap::new();
+    for jr_doc in Cursor::new(self, source) {
+      let jr_doc = jr_doc?;
+      let id:String = jr_doc.get_value("_id").unwrap_or_default();
+      if let Some(stored) = index.stored(&jr_doc) {
+        let keys = index.keys_of(&jr_doc);
+        if index.conflicts(&id, &keys) {
+          return Err(JrError::DuplicateKey(index.target()));
+        }
+        index.insert(&id, keys);
+        pages.entry(jrdb_index::entry_page(&id)).or_default().add_value(&id, stored);
+      }
+    }
+    let mut entries = JrDocument::new();
+    for (page, page_doc) in pages {
+      entries.add_value(&page, page_doc);
+    }
+
+    let mut fields_doc = JrDocument::new();
+    for (pos, field) in fields.iter().enumerate() {
+      fields_doc.add_value(&pos.to_string(), field.clone());
+    }
+    let mut index_doc = JrDocument::new();
+    index_doc.add_value("collection", collection_name.to_string());
+    index_doc.add_value("fields", fields_doc);
+    index_doc.add_value("unique", unique as i64);
+    index_doc.add("entries", entries);
+    self.set_root_path(&[INDEXES_KEY, &index.name], JrAny::JrDocument(index_doc))?;
+
+    let header = FileHeader::read(&mut self.data)?;
+    if header.flags & FLAG_INDEXES == 0 {
+      self.append_data(FLAGS_OFFSET, FLAGS_OFFSET+4, &(header.flags | FLAG_INDEXES).to_be_bytes());
+    }
+
+    self.indexes.push(index);
+    Ok(())
+  }
+
+  fn drop_collection_action(&mut self, action:&mut Action)->Result<(), JrError>{
+    let mut root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let mut collection = self.get_by_key_from_doc(&root, &action.from, 1)?;
+    if !collection.found {
+      return Err(JrError::NotFound(action.from.clone()));
+    }
+    let size_added = self.delete_key_by_header(&mut collection);
+    self.add_size(&mut root, size_added);
+
+    let names:Vec<String> = self.indexes.iter()
+      .filter(|index| index.collection == action.from)
+      .map(|index| index.name.clone())
+      .collect();
+    for name in names.iter() {
+      let mut root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+      self.delete_path_by_pos(&[INDEXES_KEY, name], &mut root)?;
+    }
+    self.indexes.retain(|index| index.collection != action.from);
+    Ok(())
+  }
+
+  fn rename_collection_action(&mut self, action:&mut Action)->Result<(), JrError>{
+    let new_name = action.keys[0].clone();
+    check_key(&new_name)?;
+    if new_name.is_empty() || new_name.starts_with('$') || new_name.contains('.') {
+      return Err(JrError::InvalidQuery(format!("invalid collection name: {}", new_name)));
+    }
+
+    let mut root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let collection = self.get_by_key_from_doc(&root, &action.from, 1)?;
+    if !collection.found {
+      return Err(JrError::NotFound(action.from.clone()));
+    }
+    if self.get_by_key_from_doc(&root, &new_name, 1)?.found {
+      return Err(JrError::InvalidQuery(format!("collection exists: {}", new_name)));
+    }
+
+    //only the header holds the name, the documents are kept as they are
+    let header = self.new_attr_header(
+      1,
+      (collection.content_end - collection.content_start) as u32,
+      new_name.clone(),
+      collection.depth
+    );
+    let size_added = header.len() as i64 - (collection.content_start - collection.header_start) as i64;
+    self.append_data(collection.header_start, collection.content_start, &header);
+    self.update_len(collection.header_start, collection.content_length);
+    self.add_size(&mut root, size_added);
+
+    for pos in 0..self.indexes.len() {
+      if self.indexes[pos].collection != action.from {
+        continue;
+      }
+      self.indexes[pos].collection = new_name.clone();
+      let name = self.indexes[pos].name.clone();
+      self.set_root_path(&[INDEXES_KEY, &name, "collection"], JrAny::JrString(JrString::new(new_name.clone())))?;
+    }
+    Ok(())
+  }
+
+  /// Read the indexes stored in the database into memory.
+  fn load_indexes(&mut self)->Result<(), JrError>{
+    let root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let header = self.get_by_key_from_doc(&root, INDEXES_KEY, 0)?;
+    if !header.found {
+      self.indexes = vec![];
+      return Ok(());
+    }
+
+    let mut stored = self.get_doc_by_header(&header)?;
+    let mut indexes = vec![];
+    let mut valid = true;
+    stored.loop_key(&mut |name, data| {
+      let index_doc = match data {
+        JrAny::JrDocument(index_doc) => index_doc,
+        _ => {
+          valid = false;
+          return;
+        },
+      };
+      let collection:Result<String, &str> = index_doc.get_value("collection");
+      let fields_doc:Result<JrDocument, &str> = index_doc.get_value("fields");
+      let entries:Result<JrDocument, &str> = index_doc.get_value("entries");
+      let unique:i64 = index_doc.get_value("unique").unwrap_or(0);
+      let mut fields = vec![];
+      if let Ok(fields_doc) = &fields_doc {
+        while let Ok(field) = fields_doc.get_value(&fields.len().to_string()) {
+          fields.push(field);
+        }
+      }
+      match (collection, entries) {
+        (Ok(collection), Ok(mut entries)) if !fields.is_empty() => {
+          let mut index = Index::new(name, &collection, &fields, unique != 0);
+          entries.loop_key(&mut |_, page| {
+            match page {
+              JrAny::JrDocument(page) => page.loop_key(&mut |id, stored| {
+                match stored {
+                  JrAny::JrDocument(stored) => index.load(id, stored),
+                  _ => valid = false,
+                }
+              }),
+              _ => valid = false,
+            }
+          });
+          indexes.push(index);
+        },
+        _ => valid = false,
+      }
+    });
+
+    if !valid {
+      return Err(JrError::Corrupt{ offset:header.header_start });
+    }
+    self.indexes = indexes;
+    Ok(())
+  }
+
+  /// Bring the indexes of `collection` up to date with the document `id`.
+  fn index_document(&mut self, collection:&str, id:&str, jr_doc:&JrDocument)->Result<(), JrError>{
+    for pos in 0..self.indexes.len() {
+      if self.indexes[pos].collection != collection {
+        continue;
+      }
+      let keys = self.indexes[pos].keys_of(jr_doc);
+      if self.indexes[pos].conflicts(id, &keys) {
+        return Err(JrError::DuplicateKey(self.indexes[pos].target()));
+      }
+      if !self.indexes[pos].insert(id, keys) {
+        continue;
+      }
+
+      let name = self.indexes[pos].name.clone();
+      let page = jrdb_index::entry_page(id);
+      let path = [INDEXES_KEY, &name, "entries", &page, id];
+      match self.indexes[pos].stored(jr_doc) {
+        Some(stored) => self.set_root_path(&path, JrAny::JrDocument(stored))?,
+        None => {
+          let mut root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+          self.delete_path_by_pos(&path, &mut root)?;
+        },
+      }
+    }
+    Ok(())
+  }
+
+  /// Remove the document `id` from the indexes of `collection`.
+  fn unindex_document(&mut self, collection:&str, id:&str)->Result<(), JrError>{
+    for pos in 0..self.indexes.len() {
+      if self.indexes[pos].collection != collection || !self.indexes[pos].remove(id) {
+        continue;
+      }
+      let name = self.indexes[pos].name.clone();
+      let page = jrdb_index::entry_page(id);
+      let mut root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+      self.delete_path_by_pos(&[INDEXES_KEY, &name, "entries", &page, id], &mut root)?;
+    }
+    Ok(())
+  }
 
-    self
+  /// Set the value at `path` from the root document.
+  fn set_root_path(&mut self, path:&[&str], mut data:JrAny)->Result<(), JrError>{
+    let mut root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    self.update_path_by_pos(path, &mut data, &mut root)?;
+    Ok(())
   }
 
-  /// Update data from database
-  /// 
-  /// # Examples
-  /// ```
-  /// use jrdb::Database;
-  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
-  /// 
-  /// fn main(){
-  ///   let db:Database = Database::from("main");
-  /// 
-  ///   //create JrDocument
-  ///   let mut doc = JrDocument::new();
-  ///   doc.add_value("name", String::from("Joel"));
-  ///   doc.add_value("pass", String::from("ILoveErd"));
-  ///   doc.add_value("age", 30);
-  /// 
-  ///   //create collection "users" if not exist
-  ///   db.insert("users", doc).execute();
-  ///   
-  ///   //select all data from collection "users"
-  ///   let collection: JrCollection = db.select("users").execute();
-  ///   collection.print(0);
-  /// 
-  ///   //create JrDocument for update
-  ///   let mut updated_doc = JrDocument::new();
-  ///   updated_doc.add_value("name", String::from("Mathew"));
-  /// 
-  ///   //update all data from collection "users"
-  ///   db.update("users", updated_doc).execute();
-  /// 
-  ///   let collection: JrCollection = db.select("users").execute();
-  ///   collection.print(0);
-  /// }
-  /// ```
-  pub fn update(&mut self, from:&str, doc:JrDocument)->&mut Self{
-    self.actions.push(Action{
-      action_type:ActionType::Update,
-      condition:cond_true!(),
-      from:from.into(),
-      keys:vec![],
-      data:vec![doc]
-    });
-    self
+  /// Ids of the documents an action has to read found with the indexes of
+  /// its collection and its `_id`, `None` when every document is read.
+  fn candidate_ids(&self, action:&Action)->Option<BTreeSet<String>>{
+    self.plan_scan(action).0
   }
 
-  /// Delete data from database
-  /// 
-  /// # Examples
-  /// ```
-  /// use jrdb::Database;
-  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
-  /// 
-  /// fn main(){
-  ///   let db:Database = Database::from("main");
-  /// 
-  ///   //create JrDocument
-  ///   let mut doc = JrDocument::new();
-  ///   doc.add_value("name", String::from("Joel"));
-  ///   doc.add_value("pass", String::from("ILoveErd"));
-  ///   doc.add_value("age", 30);
-  /// 
-  ///   //create collection "users" if not exist
-  ///   db.insert("users", doc).execute();
-  ///   
-  ///   //select all data from collection "users"
-  ///   let collection: JrCollection = db.select("users").execute();
-  ///   collection.print(0);
-  ///
-  ///   //delete all data from collection "users"
-  ///   db.delete("users").execute();
-  /// 
-  ///   let collection: JrCollection = db.select("users").execute();
-  ///   collection.print(0);
-  /// }
-  /// ```
-  pub fn delete(&mut self, from:&str)->&mut Self{
-    self.actions.push(Action{
-      action_type:ActionType::Delete,
-      condition:cond_true!(),
-      from:from.into(),
-      keys:vec![],
-      data:vec![]
-    });
-    self
+  /// Candidate ids of an action with the scan they come from.
+  fn plan_scan(&self, action:&Action)->(Option<BTreeSet<String>>, Scan){
+    let collection_name = action.from.split('.').next().unwrap_or_default();
+    let indexes:Vec<&Index> = self.indexes.iter()
+      .filter(|index| index.collection == collection_name)
+      .collect();
+    let found = jrdb_index::candidates(&indexes, &action.condition);
+
+    match (&action.id, found) {
+      (Some(id), Some((mut ids, _))) => {
+        ids.retain(|elem| elem == id);
+        (Some(ids), Scan::Id(id.clone()))
+      },
+      (Some(id), None) => (Some(BTreeSet::from([id.clone()])), Scan::Id(id.clone())),
+      (None, Some((ids, used))) => (Some(ids), Scan::Index(used)),
+      (None, None) => (None, Scan::Full),
+    }
   }
 
-  /// Apply condition while select, delete or update the database
-  /// 
-  /// # Examples
-  /// ```
-  /// use jrdb::Database;
-  /// use jrdb::jrdb_type::{ AddGetValue, JrCollection, JrDocument };
-  /// use jrdb::exp;
-  /// 
-  /// fn main(){
-  ///   let db:Database = Database::from("main");
-  /// 
-  ///   //create JrDocument
-  ///   let mut doc = JrDocument::new();
-  ///   doc.add_value("name", String::from("Joel"));
-  ///   doc.add_value("pass", String::from("ILoveErd"));
-  ///   doc.add_value("age", 30);
-  /// 
-  ///   //create collection "users" if not exist
-  ///   db.insert("users", doc).execute();
-  ///   
-  ///   //select all data from collection "users"
-  ///   let collection: JrCollection = 
-  ///     db.select("users")
-  ///     .condition(exp! {"name" ;== "'Mathew'"})
-  ///     .execute();
-  ///   
-  ///   //nothing will show since no document with in 'users' with name 'Manthew'
-  ///   collection.print(0);
-  /// 
-  ///   //select all data from collection "users"
-  ///   let collection: JrCollection = 
-  ///     db.select("users")
-  ///     .condition(exp! {"name" ;== "'Joel'"})
-  ///     .execute();
-  ///   
-  ///   //shows document with name 'Joel'
-  ///   collection.print(0);
-  /// }
-  /// ```
-  pub fn condition(&mut self, cond:JrCondition)->&mut Self{
-    let i = self.actions.len();
-    self.actions[i-1].condition = cond;
-    self
+  /// `_id` of every document of `collection`, read from their headers only.
+  fn document_ids(&self, collection:&HeaderDetail)->Result<BTreeSet<String>, JrError>{
+    let mut ids = BTreeSet::new();
+    let mut pos = collection.content_start;
+    while pos < collection.content_end {
+      let header = self.get_child_header_by_pos(collection, pos)?;
+      pos = header.content_end;
+      ids.insert(header.key);
+    }
+    Ok(ids)
+  }
+
+  /// Number of documents in a collection, counted from their headers only.
+  fn count_documents(&mut self, collection_name:&str)->Result<usize, JrError>{
+    let root = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let collection = self.get_by_key_from_doc(&root, collection_name, 1)?;
+    if !collection.found {
+      return Ok(0);
+    }
+    let mut count = 0;
+    let mut pos = collection.content_start;
+    while pos < collection.content_end {
+      pos = self.get_child_header_by_pos(&collection, pos)?.content_end;
+      count += 1;
+    }
+    Ok(count)
+  }
+
+  fn update_action(&mut self, action:&mut Action)->Result<ExecuteResult, JrError>{
+    action.data[0].check_keys()?;
+    Database::check_id_unchanged(&action.from, &mut action.data[0])?;
+    Database::check_operators(&action.operators)?;
+    let mut header_detail = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let mut collection_header = self.get_by_key_from_doc(&header_detail, action.from.split('.').nth(0).unwrap(), 1)?;
+    let ids = self.candidate_ids(action);
+    self.update_with_condition(action, &mut header_detail, &mut collection_header, ids.as_ref())
+  }
+
+  fn upsert_action(&mut self, action:&mut Action)->Result<ExecuteResult, JrError>{
+    let result = self.update_action(action)?;
+    if result.matched > 0 {
+      return Ok(result);
+    }
+
+    let mut jr_doc = JrDocument::new();
+    if action.merge_condition {
+      Database::set_equality_fields(&action.condition, &mut jr_doc);
+    }
+    let prefix:Vec<&str> = action.from.split('.').skip(1).collect();
+    Database::set_doc_paths(&mut jr_doc, &prefix, &mut action.data[0]);
+
+    let mut insert = Action{
+      returning:action.returning,
+      data:vec![jr_doc],
+      ..Action::new(ActionType::Insert, &action.from)
+    };
+    let mut result = self.insert_action(&mut insert)?;
+    if !action.operators.is_empty() {
+      let mut apply = Action{
+        id:Some(result.inserted_ids[0].clone()),
+        operators:action.operators.clone(),
+        returning:action.returning,
+        data:vec![JrDocument::new()],
+        ..Action::new(ActionType::Update, &action.from)
+      };
+      result.after = self.update_action(&mut apply)?.after;
+    }
+    Ok(result)
+  }
+
+  fn replace_action(&mut self, action:&mut Action)->Result<ExecuteResult, JrError>{
+    let mut doc = JrDocument::new();
+    Database::set_doc_paths(&mut doc, &[], &mut action.data[0]);
+    //an _id in the document picks the document to replace
+    if let Some(id) = Database::take_id(&mut doc)? {
+      if action.id.as_ref().is_some_and(|elem| *elem != id) {
+        return Err(JrError::InvalidQuery("_id of a replaced document can not change".into()));
+      }
+      action.id = Some(id);
+    }
+    action.data[0] = doc;
+    self.update_action(action)
   }
 
-  fn insert_action(&mut self, action:&mut Action){
-    //let action = self.actions[i];
-    let mut header_detail = self.get_header_detail_by_pos(0);
-    self.find_and_insert(&action.from, &mut header_detail, &mut action.data[0]);
-    fs::write(format!("{}.db", &self.file_name),&self.data).unwrap();
-    //self.execute();
+  /// Set each key of `doc`, a dot separated path, in `jr_doc` under `prefix`.
+  fn set_doc_paths(jr_doc:&mut JrDocument, prefix:&[&str], doc:&mut JrDocument){
+    doc.loop_key(&mut |key, data| {
+      let mut path = prefix.to_vec();
+      path.extend(key.split('.'));
+      jr_doc.set_path(&path.join("."), data.clone());
+    });
   }
 
-  fn select_action(&mut self, action:&mut Action)->JrCollection{
-    let mut header_detail = self.get_header_detail_by_pos(0);
-    let mut collection_header = self.get_by_key_from_doc(&mut header_detail, action.from.split('.').nth(0).unwrap(), 1);
+  /// Set the fields `condition` compares with `==` to a literal, looking into
+  /// `and` conditions only as the fields of an `or` may not all be needed.
+  fn set_equality_fields(condition:&JrCondition, jr_doc:&mut JrDocument){
+    match condition.cond_type() {
+      ConditionType::And => {
+        for elem in condition.conditions().iter() {
+          Database::set_equality_fields(elem, jr_doc);
+        }
+      },
+      ConditionType::Eq => {
+        if let (JrOperand::Field(path), JrOperand::Literal(value)) = condition.expression() {
+          jr_doc.set_path(path, value.clone());
+        }
+      },
+      _ => {},
+    }
+  }
 
-    self.select_with_condition(&action.from, &mut header_detail,&mut collection_header, &action.condition)
+  /// The `_id` is the key of the document header, an update setting it as
+  /// a field of the document under `from` is rejected.
+  fn check_id_unchanged(from:&str, doc:&mut JrDocument)->Result<(), JrError>{
+    let mut top_keys = vec![];
+    match from.split('.').nth(1) {
+      Some(key) => top_keys.push(key.to_string()),
+      None => doc.loop_key(&mut |key, _| top_keys.push(key.split('.').next().unwrap_or_default().to_string())),
+    }
+    if top_keys.iter().any(|key| key == "_id") {
+      return Err(JrError::InvalidQuery("_id can not be changed by an update".into()));
+    }
+    Ok(())
   }
 
-  fn update_action(&mut self, action:&mut Action){
-    let mut header_detail = self.get_header_detail_by_pos(0);
-    let mut collection_header = self.get_by_key_from_doc(&mut header_detail, action.from.split('.').nth(0).unwrap(), 1);
-    self.update_with_condition(&action.from, &mut header_detail, &mut collection_header, &action.condition, &mut action.data[0]);
-    fs::write(format!("{}.db", &self.file_name),&self.data).unwrap();
+  fn check_operators(operators:&[UpdateOp])->Result<(), JrError>{
+    for op in operators.iter() {
+      //documents written by the operator
+      let (paths, items) = match op {
+        UpdateOp::Push(path, item) => (vec![path], vec![item]),
+        UpdateOp::Rename(from, to) => (vec![from, to], vec![]),
+        UpdateOp::Min(path, value) | UpdateOp::Max(path, value) => match value {
+          JrAny::JrDocument(item) => (vec![path], vec![item]),
+          JrAny::JrCollection(collec) => (vec![path], (0..collec.len()).map(|i| collec.get(i)).collect()),
+          _ => (vec![path], vec![]),
+        },
+        UpdateOp::Inc(path, _)
+        | UpdateOp::Unset(path)
+        | UpdateOp::Pull(path, _) => (vec![path], vec![]),
+      };
+      for path in paths {
+        if path.split('.').next() == Some("_id") {
+          return Err(JrError::InvalidQuery("_id can not be changed by an operator".into()));
+        }
+        for segment in path.split('.') {
+          check_key(segment)?;
+        }
+      }
+      for item in items {
+        item.check_keys()?;
+      }
+    }
+    Ok(())
   }
 
   fn update_with_condition(
-    &mut self, _from:&str, 
-    parent:&mut HeaderDetail, target:&mut HeaderDetail, 
-    condition:&JrCondition, doc:&mut JrDocument
-  ){
+    &mut self, action:&mut Action,
+    parent:&mut HeaderDetail, target:&mut HeaderDetail, ids:Option<&BTreeSet<String>>
+  )->Result<ExecuteResult, JrError>{
+    let Action{ action_type, from, condition, data, operators, returning, .. } = action;
+    let doc = &mut data[0];
+    let mut result = ExecuteResult::default();
     if target.found {
       let mut collect_size_added = 0;
+      let indexed = self.indexes.iter().any(|index| index.collection == target.key);
+      //documents after the update, their indexes are updated once the loop
+      //is done as the index data may sit before the collection
+      let mut updated = vec![];
 
       //start looping item in target one by one
-      self.loop_item_from_bytes(parent, target, &mut |db, collect_parent, collect_target| {
-        let mut jr_doc = JrDocument::new();
-
-        db.loop_item_from_bytes(collect_parent, collect_target, &mut |db, _, doc_target| {
-          db.add_content_by_header(&mut jr_doc, doc_target);
-        });
+      self.loop_documents(parent, target, ids, &mut |db, collect_parent, collect_target| {
+        let jr_doc = db.read_document(collect_parent, collect_target, None)?;
 
         if condition.result(&jr_doc) {
+          result.matched += 1;
           let mut doc_size_added = 0;
-          let mut start_pos = collect_target.content_start;
-          doc.loop_key(&mut |key, data|{
-            let val = db.update_key_by_pos(
-              key, 
-              data, 
-              start_pos, 
-              collect_target
-            );
-            start_pos = val.0;
-            doc_size_added += val.1;
-          });
+          let mut set_result = Ok(());
+          let mut set_keys = false;
+          if let ActionType::Replace = action_type {
+            let path:Vec<&str> = from.split('.').skip(1).collect();
+            doc_size_added += db.replace_document(&path, doc, collect_target)?;
+            set_keys = true;
+          }else{
+            doc.loop_key(&mut |key, data|{
+              if set_result.is_err() {
+                return;
+              }
+              set_keys = true;
+              let mut path:Vec<&str> = from.split('.').skip(1).collect();
+              path.extend(key.split('.'));
+              match db.update_path_by_pos(&path, data, collect_target) {
+                Ok(size_added) => doc_size_added += size_added,
+                Err(e) => set_result = Err(e),
+              }
+            });
+          }
+          set_result?;
+
+          if !operators.is_empty() {
+            //operators see the keys set above
+            let mut jr_doc = if set_keys {
+              db.read_document(collect_parent, collect_target, None)?
+            }else{
+              jr_doc.clone()
+            };
+            let prefix:Vec<&str> = from.split('.').skip(1).collect();
+            for op in operators.iter() {
+              doc_size_added += db.apply_operator(op, &prefix, &mut jr_doc, collect_target)?;
+            }
+          }
           collect_size_added += doc_size_added;
           collect_parent.content_size = (collect_parent.content_size as i64 + doc_size_added) as usize;
           collect_parent.content_end = collect_parent.content_size + collect_parent.header_start;
 
+          let after = db.read_document(collect_parent, collect_target, None)?;
+          if jr_doc.clone().get_bytes(0) != after.clone().get_bytes(0) {
+            result.modified += 1;
+          }
+          if *returning {
+            result.before.add(jr_doc);
+            result.after.add(after.clone());
+          }
+          if indexed {
+            updated.push(after);
+          }
         }
-
-      });
+        Ok(())
+      })?;
 
       self.update_size(
         target.header_start,
@@ -420,80 +2520,216 @@ impl Database{
       );
       parent.content_size = new_content_size;
       parent.content_end = parent.header_start + new_content_size;
+
+      for jr_doc in updated.iter() {
+        let id:String = jr_doc.get_value("_id").unwrap_or_default();
+        self.index_document(&target.key, &id, jr_doc)?;
+      }
     }
+    Ok(result)
   }
 
-  fn update_key_by_pos(&mut self, key:&str, data:&mut JrAny, start:usize, target_header:&mut HeaderDetail)->(usize, i64){
-    let header = self.get_pos_by_key(start, target_header.content_end, key, 255);
-
-    let mut data_type = 255;
-    let mut bytes_data = if let JrAny::JrI64(v) = data{
-      data_type = 3;
-      v.get_bytes(target_header.depth+1)
-    }else if let JrAny::JrString(v) = data {
-      data_type = 2;
-      v.get_bytes(target_header.depth+1)
-    }else{
-      vec![]
+  /// Apply an update operator to `jr_doc`, the document of `target`, where
+  /// `prefix` is the path of the updated document inside it. Returns the
+  /// number of bytes added to the target.
+  fn apply_operator(&mut self, op:&UpdateOp, prefix:&[&str], jr_doc:&mut JrDocument, target:&mut HeaderDetail)->Result<i64, JrError>{
+    let full_path = |path:&str| {
+      let mut parts = prefix.to_vec();
+      parts.extend(path.split('.'));
+      parts.join(".")
     };
 
-    let mut attr_header = self.new_attr_header(data_type, bytes_data.len() as u32 , key.into(), target_header.depth+1);
-    attr_header.append(&mut bytes_data);
+    match op {
+      UpdateOp::Inc(path, amount) => {
+        let path = full_path(path);
+        let value = match jr_doc.get_path(&path) {
+          None => *amount,
+          Some(JrAny::JrI64(value)) => value.get().saturating_add(*amount),
+          Some(_) => return Err(JrError::TypeMismatch(path)),
+        };
+        self.set_doc_path(&path, JrAny::JrI64(JrI64::new(value)), jr_doc, target)
+      },
+      UpdateOp::Unset(path) => self.unset_doc_path(&full_path(path), jr_doc, target),
+      UpdateOp::Push(path, item) => {
+        let path = full_path(path);
+        let mut collec = match jr_doc.get_path(&path) {
+          None => JrCollection::new(),
+          Some(JrAny::JrCollection(collec)) => collec.clone(),
+          Some(_) => return Err(JrError::TypeMismatch(path)),
+        };
+        collec.add(item.clone());
+        self.set_doc_path(&path, JrAny::JrCollection(collec), jr_doc, target)
+      },
+      UpdateOp::Pull(path, condition) => {
+        let path = full_path(path);
+        let kept = match jr_doc.get_path(&path) {
+          None => return Ok(0),
+          Some(JrAny::JrCollection(collec)) => {
+            let mut kept = JrCollection::new();
+            for pos in 0..collec.len() {
+              if !condition.result(collec.get(pos)) {
+                kept.add(collec.get(pos).clone());
+              }
+            }
+            if kept.len() == collec.len() {
+              return Ok(0);
+            }
+            kept
+          },
+          Some(_) => return Err(JrError::TypeMismatch(path)),
+        };
+        self.set_doc_path(&path, JrAny::JrCollection(kept), jr_doc, target)
+      },
+      UpdateOp::Rename(from, to) => {
+        let (from, to) = (full_path(from), full_path(to));
+        let value = match jr_doc.get_path(&from) {
+          Some(value) if from != to => value.clone(),
+          _ => return Ok(0),
+        };
+        let size_added = self.unset_doc_path(&from, jr_doc, target)?;
+        Ok(size_added + self.set_doc_path(&to, value, jr_doc, target)?)
+      },
+      UpdateOp::Min(path, value) | UpdateOp::Max(path, value) => {
+        let path = full_path(path);
+        let ordering = if let UpdateOp::Min(..) = op { Ordering::Less } else { Ordering::Greater };
+        if let Some(current) = jr_doc.get_path(&path) {
+          if Database::compare_values(Some(value), Some(current)) != ordering {
+            return Ok(0);
+          }
+        }
+        self.set_doc_path(&path, value.clone(), jr_doc, target)
+      },
+    }
+  }
+
+  /// Rewrite the content of the document of `target`, or the document at
+  /// `path` inside it, with `doc`. Returns the number of bytes added to the
+  /// target.
+  fn replace_document(&mut self, path:&[&str], doc:&JrDocument, target:&mut HeaderDetail)->Result<i64, JrError>{
+    if !path.is_empty() {
+      return self.update_path_by_pos(path, &mut JrAny::JrDocument(doc.clone()), target);
+    }
+    let bytes = doc.clone().get_bytes(target.depth);
+    self.append_data(target.content_start, target.content_end, &bytes);
+    let size_added = bytes.len() as i64 - (target.content_end - target.content_start) as i64;
+    self.add_size(target, size_added);
+    Ok(size_added)
+  }
+
+  /// Set `path` of the document of `target` and of its copy `jr_doc`.
+  fn set_doc_path(&mut self, path:&str, mut value:JrAny, jr_doc:&mut JrDocument, target:&mut HeaderDetail)->Result<i64, JrError>{
+    let parts:Vec<&str> = path.split('.').collect();
+    let size_added = self.update_path_by_pos(&parts, &mut value, target)?;
+    jr_doc.set_path(path, value);
+    Ok(size_added)
+  }
+
+  /// Remove `path` from the document of `target` and from its copy `jr_doc`.
+  fn unset_doc_path(&mut self, path:&str, jr_doc:&mut JrDocument, target:&mut HeaderDetail)->Result<i64, JrError>{
+    let parts:Vec<&str> = path.split('.').collect();
+    let size_added = self.delete_path_by_pos(&parts, target)?;
+    jr_doc.remove_path(path);
+    Ok(size_added)
+  }
+
+  fn update_key_by_pos(&mut self, key:&str, data:&mut JrAny, start:usize, target_header:&mut HeaderDetail)->Result<(usize, i64), JrError>{
+    let header = self.get_pos_by_key(start, target_header.content_end, key, 255)?;
+
+    let attr_header = data.get_attr_bytes(key.into(), target_header.depth+1);
 
     let val = if header.found {
-      self.append_data(header.header_start, header.content_end, &mut attr_header);
+      self.append_data(header.header_start, header.content_end, &attr_header);
       (
         header.header_start + attr_header.len(), 
         attr_header.len() as i64 - header.content_size as i64
       )
     }else{
-      self.append_data(target_header.content_end, target_header.content_end, &mut attr_header);
+      self.append_data(target_header.content_end, target_header.content_end, &attr_header);
       (
         start, 
         attr_header.len() as i64
       )
     };
 
-    let new_content_size = (target_header.content_size as i64 + val.1) as usize;
+    self.add_size(target_header, val.1);
+
+    Ok(val)
+  }
+
+  /// Set the value at `path` inside the document of `target_header`, missing
+  /// documents along the path are created. Returns the number of bytes added
+  /// to the target.
+  fn update_path_by_pos(&mut self, path:&[&str], data:&mut JrAny, target_header:&mut HeaderDetail)->Result<i64, JrError>{
+    let is_collection = target_header.content_type == 1;
+    if path.len() == 1 && !is_collection {
+      return Ok(self.update_key_by_pos(path[0], data, target_header.content_start, target_header)?.1);
+    }
+
+    let mut header = self.get_pos_by_key(target_header.content_start, target_header.content_end, path[0], 255)?;
+    let size_added = if path.len() == 1 {
+      //items of a collection are not replaced by path
+      return Err(JrError::TypeMismatch(path[0].into()));
+    }else if header.found && header.content_type <= 1 {
+      self.update_path_by_pos(&path[1..], data, &mut header)?
+    }else if header.found {
+      return Err(JrError::TypeMismatch(path[0].into()));
+    }else if is_collection {
+      return Err(JrError::NotFound(path[0].into()));
+    }else{
+      let mut nested = JrDocument::new();
+      nested.set_path(&path[1..].join("."), data.clone());
+      let mut nested = JrAny::JrDocument(nested);
+      return Ok(self.update_key_by_pos(path[0], &mut nested, target_header.content_start, target_header)?.1);
+    };
+
+    self.add_size(target_header, size_added);
+    Ok(size_added)
+  }
+
+  fn add_size(&mut self, header:&mut HeaderDetail, size_added:i64){
+    let new_content_size = (header.content_size as i64 + size_added) as usize;
 
     self.update_size(
-      target_header.header_start,
+      header.header_start,
       new_content_size
     );
 
-    target_header.content_size = new_content_size;
-    target_header.content_end = target_header.header_start + new_content_size;
-
-    val
+    header.content_size = new_content_size;
+    header.content_end = header.header_start + new_content_size;
   }
 
-  fn delete_action(&mut self, action:&mut Action){
-    let mut header_detail = self.get_header_detail_by_pos(0);
-    let mut collection_header = self.get_by_key_from_doc(&mut header_detail, action.from.split('.').nth(0).unwrap(), 1);
-    self.delete_with_condition(&action.from, &mut header_detail, &mut collection_header, &action.condition);
-    fs::write(format!("{}.db", &self.file_name),&self.data).unwrap();
+  fn delete_action(&mut self, action:&mut Action)->Result<ExecuteResult, JrError>{
+    let mut header_detail = self.get_header_detail_by_pos(FILE_HEADER_LEN)?;
+    let mut collection_header = self.get_by_key_from_doc(&header_detail, action.from.split('.').nth(0).unwrap(), 1)?;
+    let ids = self.candidate_ids(action);
+    self.delete_with_condition(action, &mut header_detail, &mut collection_header, ids.as_ref())
   }
 
   fn delete_with_condition(
-    &mut self, _from:&str,parent:&mut HeaderDetail, target:&mut HeaderDetail, 
-    condition:&JrCondition,
-  ){
+    &mut self, action:&Action, parent:&mut HeaderDetail, target:&mut HeaderDetail,
+    ids:Option<&BTreeSet<String>>
+  )->Result<ExecuteResult, JrError>{
+    let Action{ condition, returning, .. } = action;
+    let mut result = ExecuteResult::default();
     if target.found {
       let mut collect_size_added = 0;
-      self.loop_item_from_bytes(parent, target, &mut |db, collect_parent, collect_target| {
-        let mut jr_doc = JrDocument::new();
-
-        db.loop_item_from_bytes(collect_parent, collect_target, &mut |db, _, doc_target| {
-          db.add_content_by_header(&mut jr_doc, doc_target);
-        });
+      let mut deleted = vec![];
+      self.loop_documents(parent, target, ids, &mut |db, collect_parent, collect_target| {
+        let jr_doc = db.read_document(collect_parent, collect_target, None)?;
         if condition.result(&jr_doc) {
-          
+          result.matched += 1;
+          result.deleted += 1;
+          if *returning {
+            result.before.add(jr_doc);
+          }
+          deleted.push(collect_target.key.clone());
           let doc_size_added = db.delete_key_by_header(collect_target);
           collect_size_added += doc_size_added;
           collect_parent.content_size = (collect_parent.content_size as i64 + doc_size_added) as usize;
           collect_parent.content_end = collect_parent.content_size + collect_parent.header_start;
         }
-      });
+        Ok(())
+      })?;
 
       self.update_size(
         target.header_start,
@@ -508,108 +2744,254 @@ impl Database{
       );
       parent.content_size = new_content_size;
       parent.content_end = parent.header_start + new_content_size;
+
+      for id in deleted.iter() {
+        self.unindex_document(&target.key, id)?;
+      }
     }
+    Ok(result)
+  }
+
+  /// Remove the value at `path` inside the document of `target`, returns the
+  /// number of bytes added to the target.
+  fn delete_path_by_pos(&mut self, path:&[&str], target:&mut HeaderDetail)->Result<i64, JrError>{
+    let mut header = self.get_pos_by_key(target.content_start, target.content_end, path[0], 255)?;
+    let size_added = if !header.found {
+      return Ok(0);
+    }else if path.len() == 1 {
+      self.delete_key_by_header(&mut header)
+    }else if header.content_type == 0 {
+      self.delete_path_by_pos(&path[1..], &mut header)?
+    }else{
+      return Ok(0);
+    };
+
+    self.add_size(target, size_added);
+    Ok(size_added)
   }
 
   fn delete_key_by_header(&mut self, header:&mut HeaderDetail)->i64{
-    let mut data = vec![];
-    self.append_data(header.header_start, header.content_end, &mut data);
+    let data = vec![];
+    self.append_data(header.header_start, header.content_end, &data);
     header.content_size = 0;
-    return header.header_start as i64 - header.content_end as i64
+    header.header_start as i64 - header.content_end as i64
   }
 
-  fn add_content_by_header(&mut self, jr_doc:&mut JrDocument, doc_target:&mut HeaderDetail){
-    if doc_target.content_type == 2 {
-      let _:String = jr_doc.get_value_from_db(self, doc_target);
+  fn add_content_by_header(&mut self, jr_doc:&mut JrDocument, doc_target:&mut HeaderDetail)->Result<(), JrError>{
+    if doc_target.content_type == 0 {
+      let _:JrDocument = jr_doc.get_value_from_db(self, doc_target)?;
+    } else if doc_target.content_type == 1 {
+      let _:JrCollection = jr_doc.get_value_from_db(self, doc_target)?;
+    } else if doc_target.content_type == 2 {
+      let _:String = jr_doc.get_value_from_db(self, doc_target)?;
     } else if doc_target.content_type == 3 {
-      let _:i64 = jr_doc.get_value_from_db(self, doc_target);
+      let _:i64 = jr_doc.get_value_from_db(self, doc_target)?;
     }
+    Ok(())
   }
 
-  fn select_with_condition(&mut self, _from:&str, parent:&mut HeaderDetail, target:&mut HeaderDetail, condition:&JrCondition)->JrCollection{
+  fn get_doc_by_header(&mut self, header:&HeaderDetail)->Result<JrDocument, JrError>{
+    let mut jr_doc = JrDocument::new();
+    let mut curr_pos = header.content_start;
+    while curr_pos < header.content_end {
+      let mut key_header = self.get_child_header_by_pos(header, curr_pos)?;
+      self.add_content_by_header(&mut jr_doc, &mut key_header)?;
+      curr_pos += key_header.content_size;
+    }
+    Ok(jr_doc)
+  }
 
-    let mut jr_collec = JrCollection::new();
-    if target.found {
-      //this loop the collection found
-      self.loop_item_from_bytes(parent, target, &mut |db, collect_parent, collect_target| {
-        let mut jr_doc = JrDocument::new();
-
-        let id = JrString::new( format!("{}",collect_target.key) );
-        jr_doc.add("_id", id);
-        // this loop throught the key in the item
-        db.loop_item_from_bytes(collect_parent, collect_target, &mut |db, _, doc_target| {
-          db.add_content_by_header(&mut jr_doc, doc_target);
-        });
-        if condition.result(&jr_doc) {
-          jr_collec.add(jr_doc);
-        }
-      })
+  /// Order two documents by the keys of `sort`, the first key that differs
+  /// decides.
+  fn compare_docs(a:&JrDocument, b:&JrDocument, sort:&[(String, SortOrder)])->Ordering{
+    for (path, order) in sort.iter() {
+      let ordering = Database::compare_values(a.get_path(path), b.get_path(path));
+      let ordering = match order {
+        SortOrder::Asc => ordering,
+        SortOrder::Desc => ordering.reverse(),
+      };
+      if ordering != Ordering::Equal {
+        return ordering;
+      }
+    }
+    Ordering::Equal
+  }
+
+  /// i64 and String are compared by value, values of different types are
+  /// ordered missing, i64, String then documents and collections.
+  fn compare_values(a:Option<&JrAny>, b:Option<&JrAny>)->Ordering{
+    fn rank(data:Option<&JrAny>)->u8{
+      match data {
+        None => 0,
+        Some(JrAny::JrI64(_)) => 1,
+        Some(JrAny::JrString(_)) => 2,
+        Some(_) => 3,
+      }
     }
-    jr_collec
+    match (a, b) {
+      (Some(JrAny::JrI64(a)), Some(JrAny::JrI64(b))) => a.get().cmp(b.get()),
+      (Some(JrAny::JrString(a)), Some(JrAny::JrString(b))) => a.get().cmp(b.get()),
+      _ => rank(a).cmp(&rank(b)),
+    }
+  }
+
+  /// Read the document at `header` of the collection `collection`, its `_id`
+  /// is the key of the header. With a projection only the keys it needs are
+  /// decoded.
+  fn read_document(&mut self, collection:&mut HeaderDetail, header:&mut HeaderDetail, projection:Option<&Projection>)->Result<JrDocument, JrError>{
+    let mut jr_doc = JrDocument::new();
+    jr_doc.add("_id", JrString::new(header.key.clone()));
+    self.loop_item_from_bytes(collection, header, &mut |db, _, doc_target| {
+      if projection.is_none_or(|projection| projection.decodes(&doc_target.key)) {
+        db.add_content_by_header(&mut jr_doc, doc_target)?;
+      }
+      Ok(())
+    })?;
+    Ok(jr_doc)
   }
 
-  fn get_bytes_content(&self, from:&HeaderDetail)->Vec<u8>{
-    self.data[from.content_start..from.content_end].to_vec()
+  fn get_bytes_content(&self, from:&HeaderDetail)->Result<Vec<u8>, JrError>{
+    match self.data.get(from.content_start..from.content_end) {
+      Some(data) => Ok(data.to_vec()),
+      None => Err(JrError::Corrupt{ offset:from.header_start }),
+    }
   }
 
-  fn get_by_key_from_doc(&mut self, from:&HeaderDetail, target_key:&str, target_type: u8)->HeaderDetail{
+  fn get_by_key_from_doc(&mut self, from:&HeaderDetail, target_key:&str, target_type: u8)->Result<HeaderDetail, JrError>{
     self.get_pos_by_key(from.content_start, from.content_end, target_key, target_type)
   }
 
 
-  fn loop_item_from_bytes<F>(&mut self, _parent:&mut HeaderDetail, target:&mut HeaderDetail, f:&mut F) where
-  F:FnMut(&mut Database, &mut HeaderDetail, &mut HeaderDetail) 
+  fn loop_item_from_bytes<F>(&mut self, _parent:&mut HeaderDetail, target:&mut HeaderDetail, f:&mut F)->Result<(), JrError> where
+  F:FnMut(&mut Database, &mut HeaderDetail, &mut HeaderDetail)->Result<(), JrError>
   {
     let mut curr_pos = target.content_start;
-    while curr_pos != target.content_end {
-      let mut document_header = self.get_header_detail_by_pos(curr_pos);
-      f(self, target, &mut document_header);
+    while curr_pos < target.content_end {
+      let mut document_header = self.get_child_header_by_pos(target, curr_pos)?;
+      f(self, target, &mut document_header)?;
       curr_pos += document_header.content_size;
     }
+    Ok(())
+  }
+
+  /// Loop the documents of the collection `target` like `loop_item_from_bytes`,
+  /// when `ids` is given only the documents with those `_id` are visited.
+  fn loop_documents<F>(&mut self, parent:&mut HeaderDetail, target:&mut HeaderDetail, ids:Option<&BTreeSet<String>>, f:&mut F)->Result<(), JrError> where
+  F:FnMut(&mut Database, &mut HeaderDetail, &mut HeaderDetail)->Result<(), JrError>
+  {
+    match ids {
+      Some(ids) if ids.len() <= 1 => {
+        for id in ids.iter() {
+          let mut document_header = self.get_pos_by_key(target.content_start, target.content_end, id, 0)?;
+          if document_header.found {
+            f(self, target, &mut document_header)?;
+          }
+        }
+        Ok(())
+      },
+      Some(ids) => self.loop_item_from_bytes(parent, target, &mut |db, collect_parent, collect_target| {
+        if ids.contains(&collect_target.key) {
+          f(db, collect_parent, collect_target)?;
+        }
+        Ok(())
+      }),
+      None => self.loop_item_from_bytes(parent, target, f),
+    }
   }
 
-  fn find_and_insert(&mut self, from:&str, pos:&mut HeaderDetail, doc:&mut JrDocument)->usize{
-    let mut total_bytes_added = 0;
+  /// Insert `doc` into the collection named in `from`, returns the `_id` of
+  /// the document.
+  fn find_and_insert(&mut self, from:&str, pos:&mut HeaderDetail, docs:&mut [JrDocument])->Result<Vec<String>, JrError>{
     let mut data = from.split(".");
+    let collection_name = data.nth(0).unwrap();
+    check_key(collection_name)?;
+    if collection_name.starts_with('$') {
+      return Err(JrError::InvalidQuery(format!("collection name can not start with $: {}", collection_name)));
+    }
+    for doc in docs.iter() {
+      doc.check_keys()?;
+    }
     
-    let mut collection_header = self.get_pos_by_key(pos.content_start, pos.content_end, data.nth(0).unwrap() , 1);
+    let mut collection_header = self.get_pos_by_key(pos.content_start, pos.content_end, collection_name, 1)?;
     
     if collection_header.found {
-      total_bytes_added += self.append_to_collec_bytes_end(&mut collection_header, pos, doc);
+      self.append_to_collec_bytes_end(&mut collection_header, pos, docs)
     }else{
-      let mut header = self.new_attr_header(
+      let header = self.new_attr_header(
         1,
         0,
-        format!("{}", collection_header.key),
+        collection_header.key.clone(),
         pos.depth+1
       );
       let new_arr_start = pos.content_end;
-      total_bytes_added += self.append_to_doc_bytes_end(pos, &mut header);
-      let mut collection_pos = self.get_header_detail_by_pos(new_arr_start);
-      total_bytes_added += self.append_to_collec_bytes_end(&mut collection_pos, pos, doc);
+      self.append_to_doc_bytes_end(pos, &header);
+      let mut collection_pos = self.get_header_detail_by_pos(new_arr_start)?;
+      self.append_to_collec_bytes_end(&mut collection_pos, pos, docs)
     }
-
-    total_bytes_added
   }
 
-  fn append_to_collec_bytes_end(&mut self, collection_pos:&mut HeaderDetail, parent_pos:&mut HeaderDetail, doc:&mut JrDocument)->usize{
-    let mut total_bytes_added = 0;
-    let mut total_added = 0;
-
-    total_added += 1;
+  /// Take the `_id` given by the caller out of `doc`, the id is stored as the
+  /// key of the document header instead of as an attribute.
+  fn take_id(doc:&mut JrDocument)->Result<Option<String>, JrError>{
+    match doc.remove("_id") {
+      None => Ok(None),
+      Some(JrAny::JrString(id)) => Ok(Some(id.into())),
+      Some(JrAny::JrI64(id)) => Ok(Some(i64::from(id).to_string())),
+      Some(_) => Err(JrError::TypeMismatch("_id".into())),
+    }
+  }
 
-    let id = collection_pos.content_length + total_added;
-    let mut content = doc.get_bytes(collection_pos.depth+1);
-    let mut header = self.new_attr_header(0, content.len() as u32, format!("{}",id), collection_pos.depth+1);
-    header.append(&mut content);
+  /// Append `docs` to the end of the collection in a single splice, the
+  /// collection and parent headers are updated once. Returns the `_id` of
+  /// each document.
+  fn append_to_collec_bytes_end(&mut self, collection_pos:&mut HeaderDetail, parent_pos:&mut HeaderDetail, docs:&mut [JrDocument])->Result<Vec<String>, JrError>{
+    let mut last_id = collection_pos.content_length;
+    let mut ids = Vec::with_capacity(docs.len());
+    //ids of the batch, a given id may repeat one generated earlier
+    let mut seen = BTreeSet::new();
+    //ids of the collection, read from the headers once
+    let existing = self.document_ids(collection_pos)?;
+    let mut bytes = vec![];
+
+    for doc in docs.iter_mut() {
+      let id = match Database::take_id(doc)? {
+        Some(id) => {
+          check_key(&id)?;
+          //a numeric id moves the counter past it so it is never generated
+          //again, the counter is stored as a u32 and must have room left
+          if let Ok(num) = id.parse::<u32>() {
+            if num == u32::MAX {
+              return Err(JrError::InvalidQuery(format!("numeric _id must be below {}: {}", u32::MAX, id)));
+            }
+            last_id = last_id.max(num as usize);
+          }
+          id
+        },
+        None => {
+          if last_id >= u32::MAX as usize {
+            return Err(JrError::InvalidQuery(format!("no _id left to generate in {}", collection_pos.key)));
+          }
+          last_id += 1;
+          last_id.to_string()
+        },
+      };
+      if seen.contains(&id) || existing.contains(&id) {
+        return Err(JrError::DuplicateId(id));
+      }
 
-    let len = header.len();
-    total_bytes_added += len;
+      let mut content = doc.get_bytes(collection_pos.depth+1);
+      let mut header = self.new_attr_header(0, content.len() as u32, id.clone(), collection_pos.depth+1);
+      header.append(&mut content);
+      bytes.append(&mut header);
+      seen.insert(id.clone());
+      ids.push(id);
+    }
 
+    let total_bytes_added = bytes.len();
     self.append_data(
       collection_pos.content_end, 
       collection_pos.content_end, 
-      &mut header
+      &bytes
     );
 
     self.update_size(
@@ -619,12 +3001,12 @@ impl Database{
 
     self.update_len(
       collection_pos.header_start,
-      collection_pos.content_length + total_added
+      last_id
     );
 
     collection_pos.content_size += total_bytes_added;
     collection_pos.content_end += total_bytes_added;
-    collection_pos.content_length += total_added;
+    collection_pos.content_length = last_id;
 
     self.update_size(
       parent_pos.header_start, 
@@ -633,10 +3015,10 @@ impl Database{
     parent_pos.content_size += total_bytes_added;
     parent_pos.content_end += total_bytes_added;
 
-    total_bytes_added
+    Ok(ids)
   }
 
-  fn append_to_doc_bytes_end(&mut self, pos:&mut HeaderDetail, data:&mut Vec<u8>)->usize{
+  fn append_to_doc_bytes_end(&mut self, pos:&mut HeaderDetail, data:&[u8])->usize{
     let data_len = data.len();
     self.append_data(
       pos.content_end, 
@@ -655,18 +3037,18 @@ impl Database{
 
 
   fn update_size(&mut self, start_pos:usize, len:usize){
-    let mut bytes_data = self.u32_to_4bytes_arr(len as u32);
-    self.append_data(start_pos+3, start_pos+7, &mut bytes_data)
+    let bytes_data = self.u32_to_4bytes_arr(len as u32);
+    self.append_data(start_pos+3, start_pos+7, &bytes_data)
   }
 
   fn update_len(&mut self, start_pos:usize, len:usize){
-    let mut bytes_data = self.u32_to_4bytes_arr(len as u32);
-    self.append_data(start_pos+7, start_pos+11, &mut bytes_data)
+    let bytes_data = self.u32_to_4bytes_arr(len as u32);
+    self.append_data(start_pos+7, start_pos+11, &bytes_data)
   }
 
   fn u32_to_4bytes_arr(&self, value:u32)->Vec<u8>{
     let mut attr_size_bytes:Vec<u8> = Vec::new();
-    let slice_u32: &[u32] = &*vec![value];
+    let slice_u32: &[u32] = &[value];
     for &n in slice_u32 {
       let _ = attr_size_bytes.write_u32::<BigEndian>(n);
     }
@@ -689,40 +3071,54 @@ impl Database{
     arr
   }
 
-  fn get_pos_by_key(&mut self, start_pos:usize, limit:usize, target_key:&str,target_type:u8)->HeaderDetail{
+  fn get_pos_by_key(&mut self, start_pos:usize, limit:usize, target_key:&str,target_type:u8)->Result<HeaderDetail, JrError>{
+    let mut curr_pos = start_pos;
+    while curr_pos < limit {
+      let header_detial = self.get_header_detail_by_pos(curr_pos)?;
+      if header_detial.content_end > limit {
+        return Err(JrError::Corrupt{ offset:curr_pos });
+      }
 
-    if start_pos == limit {
-      return HeaderDetail{
-        found:false,
-        key:String::from(target_key),
-        header_start:0,
-        content_start:0,
-        content_end:0,
-        content_length:0,
-        content_size:0,
-        content_type:target_type,
-        depth:0
+      if (header_detial.content_type == target_type || target_type == 255) && header_detial.key.eq(target_key) {
+        return Ok(header_detial);
       }
+      curr_pos = header_detial.content_end;
     }
 
-    let header_detial = self.get_header_detail_by_pos(start_pos);
+    Ok(HeaderDetail{
+      found:false,
+      key:String::from(target_key),
+      header_start:0,
+      content_start:0,
+      content_end:0,
+      content_length:0,
+      content_size:0,
+      content_type:target_type,
+      depth:0
+    })
+  }
 
-    if header_detial.content_type != target_type && target_type != 255{
-      self.get_pos_by_key(header_detial.content_end, limit, target_key, target_type)
-    }else{
-      if header_detial.key.eq(&String::from(target_key)){
-        header_detial
-      }else{
-        self.get_pos_by_key(header_detial.content_end, limit, target_key, target_type)
-      }
+  /// Read the header at `start_pos` and make sure it ends inside `parent`.
+  fn get_child_header_by_pos(&self, parent:&HeaderDetail, start_pos:usize)->Result<HeaderDetail, JrError>{
+    let header = self.get_header_detail_by_pos(start_pos)?;
+    if header.content_end > parent.content_end {
+      return Err(JrError::Corrupt{ offset:start_pos });
     }
-    
+    Ok(header)
   }
 
-  fn get_header_detail_by_pos(&self, start_pos:usize)->HeaderDetail{
+  fn get_header_detail_by_pos(&self, start_pos:usize)->Result<HeaderDetail, JrError>{
+    let corrupt = || JrError::Corrupt{ offset:start_pos };
+    if start_pos+7 > self.data.len() {
+      return Err(corrupt());
+    }
+
     let key_len = self.data[start_pos+2];
     let content_type = self.data[start_pos+1];
     let depth = self.data[start_pos];
+    if content_type > 3 {
+      return Err(corrupt());
+    }
     let key_start_pos = if content_type!=1{
       start_pos+7
     }else{
@@ -730,18 +3126,21 @@ impl Database{
     };
 
     let key_end_pos = key_start_pos+(key_len as usize);
-    let key_utf8_bytes = self.data[key_start_pos..key_end_pos].to_vec();
-    let key = String::from_utf8(key_utf8_bytes).unwrap();
+    let key_utf8_bytes = self.data.get(key_start_pos..key_end_pos).ok_or_else(corrupt)?.to_vec();
+    let key = String::from_utf8(key_utf8_bytes).map_err(|_| corrupt())?;
     let buffer:[u8;4] = self.to_4bytes_arr(&self.data[(start_pos+3)..(start_pos+7)]);
     let content_size = u32::from_be_bytes(buffer);
     let content_end = start_pos+content_size as usize;
+    if content_end < key_end_pos || content_end > self.data.len() {
+      return Err(corrupt());
+    }
     let mut content_length = 0;
     if content_type == 1{
       let buffer:[u8;4] = self.to_4bytes_arr(&self.data[(start_pos+7)..(start_pos+11)]);
       content_length = u32::from_be_bytes(buffer) as usize;
     }
 
-    HeaderDetail{
+    Ok(HeaderDetail{
       found:true,
       key,
       header_start:start_pos,
@@ -751,24 +3150,24 @@ impl Database{
       content_size:content_size as usize,
       content_type,
       depth
-    }
+    })
   }
 
   fn new_attr_header(&self, data_type:u8,size:u32, name:String, depth:u8)->Vec<u8>{
     let mut header:Vec<u8> = [depth,data_type,0].to_vec();
     
-    let mut key = String::from(name).into_bytes();
+    let mut key = name.into_bytes();
 
     let key_len = key.len() as u8;
     header[2] = key_len;
     let attr_size = if data_type==1 {
-      11+key_len
+      11+key_len as u32
     }else{
-      7+key_len
-    } as u32;
+      7+key_len as u32
+    };
 
     let mut attr_size_bytes:Vec<u8> = Vec::new();
-    let slice_u32: &[u32] = &*vec![attr_size+size];
+    let slice_u32: &[u32] = &[attr_size+size];
     for &n in slice_u32 {
       let _ = attr_size_bytes.write_u32::<BigEndian>(n);
     }
@@ -783,7 +3182,13 @@ impl Database{
     header
   }
 
-  fn append_data(&mut self, start_pos:usize, end_pos:usize, data:&mut Vec<u8>){
-    self.data.splice(start_pos..end_pos, data.iter().cloned());
+  fn append_data(&mut self, start_pos:usize, end_pos:usize, data:&[u8]){
+    let replaced = self.data.splice(start_pos..end_pos, data.iter().cloned()).collect();
+    self.replaced.push(replaced);
+    self.pending.push(WalSplice{
+      start:start_pos,
+      end:end_pos,
+      data:data.to_vec()
+    });
   }
 }
\ No newline at end of file
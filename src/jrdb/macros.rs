@@ -7,7 +7,7 @@ pub mod macros{
       ),+ $(,)?
     } => {
       {
-        use jrdb::jrdb_type::JrDocument;
+        use $crate::jrdb_type::{ AddGetValue, JrDocument };
         let mut doc = JrDocument::new();
         $(
           let v:$z = $y;
@@ -26,7 +26,7 @@ pub mod macros{
       ),+ $(,)?
     ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
+        use $crate::jrdb_type::JrCondition;
         let mut cond = JrCondition::and();
         $(
           cond.add_cond($x);
@@ -44,7 +44,7 @@ pub mod macros{
       ),+ $(,)?
     } => {
       {
-        use jrdb::jrdb_type::JrCondition;
+        use $crate::jrdb_type::JrCondition;
         let mut cond = JrCondition::or();
         $(
           cond.add_cond($x);
@@ -60,13 +60,8 @@ pub mod macros{
       
     } => {
       {
-        use jrdb_type::JrCondition;
-        use jrdb_type::ConditionType;
-        JrCondition::new_exp(
-          ConditionType::Eq,
-          vec![],
-          ("''".into(), "''".into())
-        )
+        use $crate::jrdb_type::JrCondition;
+        JrCondition::and()
       }
     };
   }
@@ -77,12 +72,13 @@ pub mod macros{
       $x:expr, $y:expr
     } => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::Eq,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
@@ -92,111 +88,121 @@ pub mod macros{
   macro_rules! exp {
     ( $x:expr ;== $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::Eq,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;!= $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::NEq,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;> $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::Gt,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;!> $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::NGt,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;>= $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::GtE,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;!>= $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::NGtE,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;< $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::St,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;!< $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::NSt,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;<= $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::StE,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
     ( $x:expr ;!<= $y:expr ) => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::NStE,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
@@ -209,12 +215,13 @@ pub mod macros{
       $x:expr, $y:expr
     } => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
-          ConditionType::NEqual,
+          ConditionType::NEq,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };
@@ -226,12 +233,13 @@ pub mod macros{
       $x:expr, $y:expr
     } => {
       {
-        use jrdb::jrdb_type::JrCondition;
-        use jrdb::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrCondition;
+        use $crate::jrdb_type::ConditionType;
+        use $crate::jrdb_type::JrOperand;
         JrCondition::new_exp(
           ConditionType::Gt,
           vec![],
-          ($x.into(), $y.into())
+          (JrOperand::field($x), $y.into())
         )
       }
     };